@@ -1,22 +1,22 @@
-use ::goap::{WorldState, ActionPlanner, MAX_ACTIONS, MAX_ATOMS};
+use ::goap::{WorldState, ActionPlanner, ActionId};
 use ::bitset::BitSetU64;
-use itertools::Itertools;
-use itertools::multizip;
 use std::collections::vec_deque;
+use std::sync::Arc;
 
 #[derive(PartialEq, Eq, Clone, Copy)]
-struct AStarNode {
-    ws: WorldState, // State of the world at this node
-    parentws: Option<WorldState>, // Where did we come from?
-    g: i32, // The cost so far
-    h: i32, // The heuristic for the remaining cost
-    f: i32, // g+h combined
-    action_name :&'static str // How did we get to this node?
+pub(crate) struct AStarNode {
+    pub(crate) ws: WorldState, // State of the world at this node
+    pub(crate) parentws: Option<WorldState>, // Where did we come from?
+    pub(crate) g: i32, // The cost so far
+    pub(crate) h: i32, // The heuristic for the remaining cost
+    f: i32, // g+h combined, or h alone when searching greedily
+    pub(crate) action: Option<ActionId> // How did we get to this node?
 }
 
+#[derive(Debug, Clone)]
 pub struct AStarPlan {
-    entries: vec_deque::VecDeque<(&'static str, WorldState)>,
-    cost: i32
+    pub(crate) entries: vec_deque::VecDeque<(Arc<str>, WorldState)>,
+    pub(crate) cost: i32
 }
 
 impl AStarPlan {
@@ -24,7 +24,7 @@ impl AStarPlan {
         AStarPlan{entries: Default::default(), cost: 0}
     }
 
-    pub fn iter(&self) -> vec_deque::Iter<(&'static str, WorldState)> {
+    pub fn iter(&self) -> vec_deque::Iter<'_, (Arc<str>, WorldState)> {
         self.entries.iter()
     }
 
@@ -33,22 +33,84 @@ impl AStarPlan {
     }
 }
 
+impl Default for AStarPlan {
+    fn default() -> Self {
+        AStarPlan::new()
+    }
+}
+
+/// The atoms `goal` cares about that `from` gets wrong.
+pub(crate) fn mismatch(from: &WorldState, goal: &WorldState) -> BitSetU64 {
+    (from.values & goal.cares()) ^ (goal.values & goal.cares())
+}
+
 const MAX_OPEN: usize = 1024;
 const MAX_CLOSED: usize = 1024;
 
+/// Estimate of the remaining cost from a state to the goal.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Default)]
+pub enum Heuristic {
+    /// The number of goal atoms the state gets wrong.
+    #[default]
+    Mismatch,
+    /// Always zero, which turns A* into uniform-cost search.
+    Zero
+}
+
+/// Counters from the most recent `AStar::plan` call.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct SearchStats {
+    /// Nodes taken off the open list.
+    pub expanded: usize,
+    /// Nodes added to the open list, including the start.
+    pub generated: usize,
+    /// The largest size the open list reached.
+    pub max_open: usize,
+    /// Whether the search gave up because the open or closed list was full.
+    pub overflowed: bool
+}
+
 pub struct AStar {
-    opened: Vec<AStarNode>,
-    closed: Vec<AStarNode>
+    pub(crate) opened: Vec<AStarNode>,
+    pub(crate) closed: Vec<AStarNode>,
+    heuristic: Heuristic,
+    greedy: bool,
+    stats: SearchStats
 }
 
 impl AStar {
     pub fn new() -> Self {
         AStar {
             opened: Vec::with_capacity(MAX_OPEN),
-            closed: Vec::with_capacity(MAX_CLOSED)
+            closed: Vec::with_capacity(MAX_CLOSED),
+            heuristic: Heuristic::default(),
+            greedy: false,
+            stats: SearchStats::default()
         }
     }
 
+    pub fn set_heuristic(&mut self, heuristic: Heuristic) {
+        self.heuristic = heuristic;
+    }
+
+    /// In greedy mode nodes are ranked by the heuristic alone. Plans are usually found
+    /// faster but need not be the cheapest.
+    pub fn set_greedy(&mut self, greedy: bool) {
+        self.greedy = greedy;
+    }
+
+    pub fn heuristic(&self) -> Heuristic {
+        self.heuristic
+    }
+
+    pub fn greedy(&self) -> bool {
+        self.greedy
+    }
+
+    pub fn stats(&self) -> SearchStats {
+        self.stats
+    }
+
     fn idx_in_opened(&self, ws: &WorldState) -> Option<usize> {
         self.opened.iter().position(|o| o.ws.values == ws.values)
     }
@@ -61,33 +123,38 @@ impl AStar {
         self.closed.iter().filter(|c| c.ws.values == ws.values).nth(0)
     }
 
-    fn calc_heuristic(from: &WorldState, to: &WorldState) -> i32 {
-        let care = to.dontcare ^ BitSetU64::full();
-        let diff = (from.values & care) ^ (to.values & care);
-        let dist = diff.count_ones();
-        dist as i32
+    fn calc_heuristic(&self, from: &WorldState, to: &WorldState) -> i32 {
+        match self.heuristic {
+            Heuristic::Mismatch => mismatch(from, to).count_ones() as i32,
+            Heuristic::Zero => 0
+        }
     }
 
     fn clear(&mut self) {
         self.opened.clear();
         self.closed.clear();
+        self.stats = SearchStats::default();
+    }
+
+    fn rank(&self, g: i32, h: i32) -> i32 {
+        if self.greedy { h } else { g + h }
     }
 
-    fn reconstruct_plan(&self, ap: &ActionPlanner, goal_node: &AStarNode ) -> AStarPlan {
+    pub(crate) fn reconstruct_plan(&self, ap: &ActionPlanner, goal_node: &AStarNode ) -> AStarPlan {
         let mut plan = AStarPlan::new();
-        plan.cost = goal_node.f;
+        plan.cost = goal_node.g;
         let mut current_node = Some(goal_node);
 
         while let Some(node) = current_node {
-            println!("{}", node.action_name);
-            plan.entries.push_front((node.action_name, node.ws));
-            if let Some(parentws) = node.parentws {
-                let parentws = node.parentws.as_ref().unwrap();
-                println!("idx_in_closed: {:?}", self.idx_in_closed(parentws));
-                current_node = self.entry_in_closed(parentws);
-            } else {
-                current_node = None;
-            }
+            let name = match node.action {
+                Some(action) => ap.name(action).clone(),
+                None => Arc::from("root")
+            };
+            plan.entries.push_front((name, node.ws));
+            current_node = match node.parentws {
+                Some(ref parentws) => self.entry_in_closed(parentws),
+                None => None
+            };
         }
         plan
     }
@@ -114,45 +181,42 @@ impl AStar {
 
     pub fn plan(&mut self, ap: &ActionPlanner, start: &WorldState, goal: &WorldState) -> Option<AStarPlan> {
         self.clear();
-        let mut plan = AStarPlan::new();
-        let h = Self::calc_heuristic(start, goal);
+        let h = self.calc_heuristic(start, goal);
         let n0 = AStarNode{
             ws: *start,
             parentws: None,
             g: 0,
-            h: h,
-            f: 0 + h,
-            action_name: "root"
+            h,
+            f: self.rank(0, h),
+            action: None
         };
         self.opened.push(n0);
+        self.stats.generated = 1;
+        self.stats.max_open = 1;
 
         loop {
-            if self.opened.len() == 0 {
-                println!("Did not find a path.");
+            if self.opened.is_empty() {
                 return None;
             }
             let (lowest_idx, _) = self.opened.iter()
                     .enumerate()
                     .min_by_key(|&(_, node)| node.f)
                     .unwrap();
-            let cur = match self.opened.len() {
-                1 => self.opened.first().unwrap().clone(),
-                _ => self.opened.swap_remove(lowest_idx)
-            };
+            let cur = self.opened.swap_remove(lowest_idx);
+            self.stats.expanded += 1;
 
-            let care = goal.dontcare ^ BitSetU64::full();
-            let val_match = cur.ws.values & care == goal.values & care;
-            if val_match {
-                println!("Reconstructing plan!");
+            if cur.ws.satisfies(goal) {
+                // Kept so the explored nodes include the goal.
+                self.closed.push(cur);
                 return Some(self.reconstruct_plan(ap, &cur));
             }
             self.closed.push(cur);
             if self.closed.len() == MAX_CLOSED {
-                println!("Closed set overflow");
+                self.stats.overflowed = true;
                 return None;
             }
 
-            for(name, act_cost, to_ws) in StateTransIter::new(&cur.ws, multizip(ap.actions())) {
+            for(action, act_cost, to_ws) in StateTransIter::new(&cur.ws, ap) {
                 let cost = cur.g + act_cost;
                 let idx_o = match self.idx_in_opened(&to_ws) {
                     Some(idx) if cost < self.opened.get(idx).unwrap().g => {
@@ -180,62 +244,134 @@ impl AStar {
                 };
                 if idx_o.is_none() && idx_c.is_none() {
                     let g = cost;
-                    let h = Self::calc_heuristic(&to_ws, goal);
-                    let f = g + h;
+                    let h = self.calc_heuristic(&to_ws, goal);
+                    let f = self.rank(g, h);
                     let nb = AStarNode {
                         ws: to_ws,
-                        g: g,
-                        h: h,
-                        f: f,
-                        action_name: name,
+                        g,
+                        h,
+                        f,
+                        action: Some(action),
                         parentws: Some(cur.ws)
                     };
                     self.opened.push(nb);
+                    self.stats.generated += 1;
+                    self.stats.max_open = self.stats.max_open.max(self.opened.len());
                 }
                 if self.opened.len() == MAX_OPEN {
-                    println!("Opened set overflow!");
+                    self.stats.overflowed = true;
                     return None;
                 }
             }
         }
-        Some(plan)
-
     }
+}
 
-
+impl Default for AStar {
+    fn default() -> Self {
+        AStar::new()
+    }
 }
 
-struct StateTransIter<'a, 'b, I>
-    where I: Iterator<Item = (&'b&'static str, &'b WorldState, &'b i32, &'b WorldState)> {
+/// Iterates over the actions applicable in `from`, yielding each action with its cost and
+/// the state it leads to.
+pub struct StateTransIter<'a, 'b> {
     from: &'a WorldState,
-    action_iter: I
+    ap: &'b ActionPlanner,
+    next_idx: usize
 }
 
-impl<'a, 'b, I> StateTransIter<'a, 'b, I>
-    where I: Iterator<Item = (&'b&'static str, &'b WorldState, &'b i32, &'b WorldState)> {
+impl<'a, 'b> StateTransIter<'a, 'b> {
 
-    pub fn new(from: &'a WorldState, action_iter: I) -> StateTransIter<'a, 'b, I> {
-        StateTransIter{from: from, action_iter: action_iter}
+    pub fn new(from: &'a WorldState, ap: &'b ActionPlanner) -> StateTransIter<'a, 'b> {
+        StateTransIter{from, ap, next_idx: 0}
     }
 }
 
-impl<'a, 'b, I> Iterator for StateTransIter<'a, 'b, I>
-    where I: Iterator<Item = (&'b&'static str, &'b WorldState, &'b i32, &'b WorldState)> {
-    type Item = (&'static str, i32, WorldState);
+impl<'a, 'b> Iterator for StateTransIter<'a, 'b> {
+    type Item = (ActionId, i32, WorldState);
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some((name, pre, cost, post)) = self.action_iter.next() {
-            let care = pre.dontcare ^ BitSetU64::full();
-            let met = (pre.values & care) == (self.from.values & care);
-            if met {
-                let unaffected = post.dontcare;
-                let affected = unaffected ^ BitSetU64::full();
-                let mut next = *self.from;
-                next.values = (self.from.values & unaffected) | (post.values & affected);
-                next.dontcare &= unaffected;
-                return Some((*name, *cost, next));
+        while self.next_idx < self.ap.act_pre().len() {
+            let action = ActionId(self.next_idx);
+            self.next_idx += 1;
+            if self.ap.is_applicable(action, self.from) {
+                return Some((action, self.ap.cost(action), self.ap.apply(action, self.from)));
             }
         }
         None
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{AStar, Heuristic};
+    use ::goap::{ActionPlanner, WorldState};
+    use ::test_domains::step_names;
+
+    #[test]
+    fn expands_a_lone_open_node_once() {
+        // The start is the only open node, and it ranks below its successor, so leaving it on
+        // the open list would have it picked again forever.
+        let mut builder = ActionPlanner::builder();
+        builder.action("load").requires("armedwithgun").sets("weaponloaded").build().unwrap();
+        builder.action("shoot").requires("weaponloaded").clears("enemyalive").build().unwrap();
+        let ap = builder.build();
+        let start = WorldState::builder(&ap).holds("armedwithgun").lacks("weaponloaded").holds("enemyalive")
+            .build().unwrap();
+        let goal = WorldState::builder(&ap).lacks("enemyalive").build().unwrap();
+
+        let mut astar = AStar::new();
+        assert_eq!(2, astar.plan(&ap, &start, &goal).unwrap().cost());
+        assert_eq!(3, astar.stats().expanded);
+        assert!(!astar.stats().overflowed);
+    }
+
+    #[test]
+    fn greedy_plans_report_their_action_costs() {
+        // Greedy search ranks the goal by its heuristic alone, which is zero there, while the
+        // state before it still gets a goal atom wrong.
+        let mut builder = ActionPlanner::builder();
+        builder.action("load").cost(2).requires("armedwithgun").sets("weaponloaded").build().unwrap();
+        builder.action("shoot").cost(3).requires("weaponloaded").clears("enemyalive").build().unwrap();
+        let ap = builder.build();
+        let start = WorldState::builder(&ap).holds("armedwithgun").lacks("weaponloaded").holds("enemyalive")
+            .build().unwrap();
+        let goal = WorldState::builder(&ap).lacks("enemyalive").build().unwrap();
+
+        let mut astar = AStar::new();
+        astar.set_greedy(true);
+        let plan = astar.plan(&ap, &start, &goal).unwrap();
+        let actions: i32 = step_names(&plan).iter().map(|name| ap.cost(ap.lookup_action(name).unwrap())).sum();
+        assert_eq!(5, actions);
+        assert_eq!(actions, plan.cost());
+    }
+
+    #[test]
+    fn heuristics_and_stats() {
+        let mut builder = ActionPlanner::builder();
+        builder.action("load").requires("armedwithgun").sets("weaponloaded").build().unwrap();
+        builder.action("shoot").requires("weaponloaded").clears("enemyalive").build().unwrap();
+        builder.action("pickup").sets("armedwithgun").build().unwrap();
+        builder.action("bomb").cost(5).clears("enemyalive").build().unwrap();
+        let ap = builder.build();
+        let start = WorldState::builder(&ap).holds("enemyalive").build().unwrap();
+        let goal = WorldState::builder(&ap).lacks("enemyalive").build().unwrap();
+
+        let mut astar = AStar::new();
+        assert_eq!(3, astar.plan(&ap, &start, &goal).unwrap().cost());
+        let informed = astar.stats();
+        assert!(informed.expanded > 0 && informed.generated >= informed.expanded);
+        assert!(!informed.overflowed);
+
+        astar.set_heuristic(Heuristic::Zero);
+        assert_eq!(3, astar.plan(&ap, &start, &goal).unwrap().cost());
+
+        astar.set_greedy(true);
+        astar.set_heuristic(Heuristic::Mismatch);
+        assert_eq!(5, astar.plan(&ap, &start, &goal).unwrap().cost());
+        assert_eq!(2, astar.stats().expanded);
+    }
+}
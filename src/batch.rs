@@ -0,0 +1,53 @@
+use ::goap::{WorldState, ActionPlanner};
+use ::astar::{AStar, AStarPlan};
+use rayon::prelude::*;
+
+/// Plans every `(start, goal)` query against the shared `ap`, spreading the queries over the
+/// rayon thread pool. Each worker thread reuses its own `AStar`, and the results come back in
+/// the same order as `queries`.
+pub fn plan_batch(ap: &ActionPlanner, queries: &[(WorldState, WorldState)]) -> Vec<Option<AStarPlan>> {
+    queries.par_iter()
+        .map_init(AStar::new, |astar, (start, goal)| astar.plan(ap, start, goal))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::plan_batch;
+    use ::goap::{ActionPlanner, WorldState};
+    use ::astar::AStar;
+
+    #[test]
+    fn matches_sequential_planning() {
+        let mut builder = ActionPlanner::builder();
+        builder.set_pre("load", "armedwithgun", true);
+        builder.set_post("load", "weaponloaded", true);
+        builder.set_pre("shoot", "weaponloaded", true);
+        builder.set_post("shoot", "enemyalive", false);
+        builder.set_post("pickup", "armedwithgun", true);
+        let ap = builder.build();
+
+        let mut queries = Vec::new();
+        for i in 0..64 {
+            let mut start = WorldState::new();
+            start.set(&ap, "armedwithgun", i % 2 == 0);
+            start.set(&ap, "weaponloaded", i % 3 == 0);
+            start.set(&ap, "enemyalive", true);
+            let mut goal = WorldState::new();
+            goal.set(&ap, "enemyalive", false);
+            queries.push((start, goal));
+        }
+
+        let batch = plan_batch(&ap, &queries);
+        assert_eq!(queries.len(), batch.len());
+        let mut astar = AStar::new();
+        for ((start, goal), plan) in queries.iter().zip(batch) {
+            let expected = astar.plan(&ap, start, goal).unwrap();
+            let plan = plan.unwrap();
+            assert_eq!(expected.cost(), plan.cost());
+            assert!(expected.iter().map(|e| &e.0).eq(plan.iter().map(|e| &e.0)));
+        }
+    }
+}
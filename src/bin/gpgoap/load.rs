@@ -0,0 +1,68 @@
+use gpgoap::{Problem, PddlError, parse_dsl, parse_domain, parse_problem};
+use std::fs;
+use std::path::Path;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Input {
+    Dsl,
+    Json,
+    Pddl
+}
+
+impl Input {
+    pub fn parse(name: &str) -> Option<Input> {
+        match name {
+            "dsl" => Some(Input::Dsl),
+            "json" => Some(Input::Json),
+            "pddl" => Some(Input::Pddl),
+            _ => None
+        }
+    }
+
+    /// Guesses the format from the file extension, falling back to the DSL.
+    pub fn detect(path: &str) -> Input {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Input::Json,
+            Some("pddl") => Input::Pddl,
+            _ => Input::Dsl
+        }
+    }
+}
+
+fn read(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn pddl_error(path: &str, err: PddlError) -> String {
+    match err {
+        PddlError::Parse { .. } => format!("{}:{}", path, err),
+        _ => format!("{}: {}", path, err)
+    }
+}
+
+#[cfg(feature = "serde")]
+fn parse_json(path: &str, src: &str) -> Result<Problem, String> {
+    ::serde_json::from_str(src).map_err(|err| format!("{}: {}", path, err))
+}
+
+#[cfg(not(feature = "serde"))]
+fn parse_json(_path: &str, _src: &str) -> Result<Problem, String> {
+    Err("JSON input needs gpgoap built with the `serde` feature".to_owned())
+}
+
+/// Loads a problem from `files`: one file for the DSL and JSON, or a domain followed by a
+/// problem for PDDL. Errors are prefixed with the offending file name.
+pub fn load(input: Input, files: &[String]) -> Result<Problem, String> {
+    match (input, files) {
+        (Input::Dsl, [path]) => parse_dsl(&read(path)?).map_err(|err| format!("{}:{}", path, err)),
+        (Input::Json, [path]) => parse_json(path, &read(path)?),
+        (Input::Pddl, [domain_path, problem_path]) => {
+            let domain = parse_domain(&read(domain_path)?)
+                .map_err(|err| pddl_error(domain_path, err))?;
+            parse_problem(&domain, &read(problem_path)?)
+                .map_err(|err| pddl_error(problem_path, err))
+        },
+        (Input::Pddl, _) => Err("PDDL input needs a domain file and a problem file".to_owned()),
+        _ => Err("expected a single input file".to_owned())
+    }
+}
@@ -0,0 +1,247 @@
+extern crate gpgoap;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_json;
+
+mod load;
+mod repl;
+
+use gpgoap::{AStar, AStarPlan, ActionPlanner, Diagnosis, Heuristic, SearchStats, validate_problem, write_search_dot};
+use load::Input;
+use repl::Session;
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+
+const USAGE: &str = "\
+usage: gpgoap [options] FILE [PROBLEM]
+       gpgoap --interactive [FILE [PROBLEM]]
+
+Plans from the start to the goal of a problem written in the gpgoap DSL, as JSON, or as a
+PDDL domain FILE plus PROBLEM file. In interactive mode the problem can be explored step by
+step; type `help` there for the commands.
+
+options:
+  --input dsl|json|pddl         input format (default: from the file extension)
+  --planner astar|greedy        search strategy (default: astar)
+  --heuristic mismatch|zero     remaining-cost estimate (default: mismatch)
+  --format text|json            output format (default: text)
+  --dot FILE                    write the explored search space as Graphviz DOT
+  --lint                        warn about likely mistakes in the domain before planning
+  -i, --interactive             explore the problem interactively
+  -h, --help                    print this message";
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Format {
+    Text,
+    Json
+}
+
+#[derive(PartialEq, Eq, Debug)]
+struct Options {
+    files: Vec<String>,
+    input: Option<Input>,
+    greedy: bool,
+    heuristic: Heuristic,
+    format: Format,
+    dot: Option<String>,
+    lint: bool,
+    interactive: bool
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            files: Vec::new(),
+            input: None,
+            greedy: false,
+            heuristic: Heuristic::Mismatch,
+            format: Format::Text,
+            dot: None,
+            lint: false,
+            interactive: false
+        }
+    }
+}
+
+/// Parses the command line, returning `Ok(None)` when help was asked for.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match &*arg {
+            "-h" | "--help" => return Ok(None),
+            "-i" | "--interactive" => options.interactive = true,
+            "--input" => {
+                let name = value("--input")?;
+                options.input = Some(Input::parse(&name).ok_or_else(|| format!("unknown input format `{}`", name))?);
+            },
+            "--planner" => match &*value("--planner")? {
+                "astar" => options.greedy = false,
+                "greedy" => options.greedy = true,
+                name => return Err(format!("unknown planner `{}`", name))
+            },
+            "--heuristic" => match &*value("--heuristic")? {
+                "mismatch" => options.heuristic = Heuristic::Mismatch,
+                "zero" => options.heuristic = Heuristic::Zero,
+                name => return Err(format!("unknown heuristic `{}`", name))
+            },
+            "--format" => match &*value("--format")? {
+                "text" => options.format = Format::Text,
+                "json" => options.format = Format::Json,
+                name => return Err(format!("unknown output format `{}`", name))
+            },
+            "--lint" => options.lint = true,
+            "--dot" => options.dot = Some(value("--dot")?),
+            _ if arg.starts_with('-') && arg.len() > 1 => return Err(format!("unknown option `{}`", arg)),
+            _ => options.files.push(arg)
+        }
+    }
+    if options.files.is_empty() && !options.interactive {
+        return Err("no input file given".to_owned());
+    }
+    Ok(Some(options))
+}
+
+fn print_text(ap: &ActionPlanner, result: &Result<AStarPlan, Diagnosis>, stats: &SearchStats) {
+    match *result {
+        Ok(ref plan) => {
+            println!("Plan (cost {}):", plan.cost());
+            for (i, (action, _)) in plan.iter().skip(1).enumerate() {
+                println!("  {}. {}", i + 1, action);
+            }
+        },
+        Err(ref diagnosis) => print!("No plan found.\n{}", diagnosis.display(ap))
+    }
+    println!("Expanded {} nodes, generated {}, max open {}", stats.expanded, stats.generated, stats.max_open);
+}
+
+#[cfg(feature = "serde")]
+fn print_json(ap: &ActionPlanner, result: &Result<AStarPlan, Diagnosis>, stats: &SearchStats) -> Result<(), String> {
+    let atoms = |atoms: &[gpgoap::AtomId]| atoms.iter().map(|&atom| ap.atom_name(atom).to_string()).collect::<Vec<_>>();
+    let diagnosis = result.as_ref().err().map(|diagnosis| json!({
+        "unachievable": atoms(&diagnosis.unachievable),
+        "never_applicable": diagnosis.never_applicable.iter().map(|&action| ap.name(action).to_string())
+            .collect::<Vec<_>>(),
+        "closest": gpgoap::Named::new(ap, &diagnosis.closest),
+        "missing": atoms(&diagnosis.missing)
+    }));
+    let output = json!({
+        "plan": result.as_ref().ok().map(|plan| gpgoap::Named::new(ap, plan)),
+        "diagnosis": diagnosis,
+        "stats": {
+            "expanded": stats.expanded,
+            "generated": stats.generated,
+            "max_open": stats.max_open,
+            "overflowed": stats.overflowed
+        }
+    });
+    println!("{}", output);
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn print_json(_ap: &ActionPlanner, _result: &Result<AStarPlan, Diagnosis>, _stats: &SearchStats)
+              -> Result<(), String> {
+    Err("JSON output needs gpgoap built with the `serde` feature".to_owned())
+}
+
+fn interact(options: &Options) -> Result<(), String> {
+    let mut session = Session::new();
+    if let Some(path) = options.files.first() {
+        session.load(options.input.unwrap_or_else(|| Input::detect(path)), &options.files)?;
+    }
+    let stdin = io::stdin();
+    session.run(stdin.lock(), &mut io::stdout()).map_err(|err| err.to_string())
+}
+
+/// Returns whether a plan was found.
+fn run(options: &Options) -> Result<bool, String> {
+    let input = options.input.unwrap_or_else(|| Input::detect(&options.files[0]));
+    let problem = load::load(input, &options.files)?;
+    if options.lint {
+        for lint in validate_problem(&problem) {
+            let kind = if lint.is_warning() { "warning" } else { "note" };
+            eprintln!("{}: {}", kind, lint.display(&problem.planner));
+        }
+    }
+
+    let mut astar = AStar::new();
+    astar.set_heuristic(options.heuristic);
+    astar.set_greedy(options.greedy);
+    let result = astar.plan_or_explain(&problem.planner, &problem.start, &problem.goal);
+    if let Some(ref path) = options.dot {
+        fs::write(path, write_search_dot(&astar, &problem.planner, result.as_ref().ok()))
+            .map_err(|err| format!("{}: {}", path, err))?;
+    }
+    match options.format {
+        Format::Text => print_text(&problem.planner, &result, &astar.stats()),
+        Format::Json => print_json(&problem.planner, &result, &astar.stats())?
+    }
+    Ok(result.is_ok())
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        },
+        Err(err) => {
+            eprintln!("gpgoap: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    if options.interactive {
+        if let Err(err) = interact(&options) {
+            eprintln!("gpgoap: {}", err);
+            process::exit(2);
+        }
+        return;
+    }
+    match run(&options) {
+        Ok(true) => {},
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("gpgoap: {}", err);
+            process::exit(2);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{parse_args, Format, Options};
+    use gpgoap::Heuristic;
+    use load::Input;
+
+    fn parse(args: &[&str]) -> Result<Option<Options>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_options() {
+        let options = parse(&["--planner", "greedy", "--heuristic", "zero", "--format", "json",
+                              "--input", "pddl", "--dot", "search.dot", "domain.pddl", "problem.pddl"])
+            .unwrap().unwrap();
+        assert_eq!(vec!["domain.pddl", "problem.pddl"], options.files);
+        assert_eq!(Some(Input::Pddl), options.input);
+        assert!(options.greedy);
+        assert_eq!(Heuristic::Zero, options.heuristic);
+        assert_eq!(Format::Json, options.format);
+        assert_eq!(Some("search.dot".to_owned()), options.dot);
+        assert!(!options.interactive);
+        assert!(parse(&["-i"]).unwrap().unwrap().interactive);
+
+        assert_eq!(None, parse(&["--help"]).unwrap());
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["--planner"]).is_err());
+        assert!(parse(&["--planner", "dfs", "x.goap"]).is_err());
+        assert_eq!(Input::Json, Input::detect("problem.json"));
+        assert_eq!(Input::Dsl, Input::detect("combat.goap"));
+    }
+}
@@ -0,0 +1,270 @@
+use gpgoap::{AStar, ActionPlanner, AtomId, Problem, StateTransIter, WorldState};
+use load::{self, Input};
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+commands:
+  load FILE [PROBLEM]   load a problem and go to its start state
+  state                 print the current state
+  goal [ATOM...]        print the goal, or replace it
+  set ATOM...           set atoms true, or false when written `!atom`
+  clear ATOM...         forget atoms, leaving them unspecified
+  reset                 go back to the start state
+  actions               list the actions applicable in the current state
+  apply ACTION          apply an action to the current state
+  plan [ATOM...]        plan from the current state to the goal, or to the atoms given
+  help                  print this message
+  quit                  leave
+Names containing spaces are written in double quotes.";
+
+/// Splits a command line into words, keeping double-quoted text together.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            },
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(word.clone());
+                    word.clear();
+                    in_word = false;
+                }
+            },
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_owned());
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Reads each `atom` word as that atom with `value`, and each `!atom` word with the
+/// opposite, failing on the first unknown atom.
+fn read_atoms(ap: &ActionPlanner, words: &[String], value: bool) -> Result<Vec<(AtomId, bool)>, String> {
+    words.iter()
+        .map(|word| {
+            let (name, value) = match word.strip_prefix('!') {
+                Some(name) => (name, !value),
+                None => (&**word, value)
+            };
+            ap.lookup_atom(name).map(|atom| (atom, value)).ok_or_else(|| format!("unknown atom `{}`", name))
+        })
+        .collect()
+}
+
+/// Sets the atoms `words` name on `ws` as `read_atoms` reads them. Nothing is changed if
+/// any atom is unknown.
+fn set_atoms(ap: &ActionPlanner, ws: &mut WorldState, words: &[String], value: bool) -> Result<(), String> {
+    for (atom, value) in read_atoms(ap, words, value)? {
+        ws.set_id(atom, value);
+    }
+    Ok(())
+}
+
+/// The atoms whose value differs between `before` and `after`, as `+name` or `-name`.
+fn changes(ap: &ActionPlanner, before: &WorldState, after: &WorldState) -> Vec<String> {
+    before.diff(after)
+        .filter_map(|atom| after.get(atom).map(|value| format!("{}{}", if value { '+' } else { '-' }, ap.atom_name(atom))))
+        .collect()
+}
+
+/// An interactive session over one loaded problem, tracking a current state that commands
+/// inspect and change.
+pub struct Session {
+    problem: Option<Problem>,
+    state: WorldState,
+    astar: AStar
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session { problem: None, state: WorldState::new(), astar: AStar::new() }
+    }
+
+    /// Loads a problem and moves to its start state.
+    pub fn load(&mut self, input: Input, files: &[String]) -> Result<(), String> {
+        let problem = load::load(input, files)?;
+        self.state = problem.start;
+        self.problem = Some(problem);
+        Ok(())
+    }
+
+    /// Runs one command line, returning `Ok(false)` once the user asks to quit.
+    pub fn execute<W: Write>(&mut self, line: &str, out: &mut W) -> Result<bool, String> {
+        let words = split_words(line)?;
+        let (command, args) = match words.split_first() {
+            Some((command, args)) => (&**command, args),
+            None => return Ok(true)
+        };
+        match command {
+            "quit" | "exit" => return Ok(false),
+            "help" => writeln!(out, "{}", HELP).map_err(|err| err.to_string())?,
+            "load" => {
+                let input = args.first().map(|path| Input::detect(path)).ok_or("load needs a file")?;
+                self.load(input, args)?;
+            },
+            _ => self.execute_on_problem(command, args, out).map_err(|err| err.to_string())?
+        }
+        Ok(true)
+    }
+
+    fn execute_on_problem<W: Write>(&mut self, command: &str, args: &[String], out: &mut W)
+                                    -> Result<(), Box<dyn Error>> {
+        let problem = self.problem.as_mut().ok_or("no problem loaded; use `load FILE`")?;
+        let ap = &problem.planner;
+        match command {
+            "state" => write!(out, "{:?}", self.state.debug_fmt(ap))?,
+            "goal" if args.is_empty() => write!(out, "{:?}", problem.goal.debug_fmt(ap))?,
+            "goal" => {
+                let mut goal = WorldState::new();
+                set_atoms(ap, &mut goal, args, true)?;
+                problem.goal = goal;
+            },
+            "set" => set_atoms(ap, &mut self.state, args, true)?,
+            "clear" => {
+                for (atom, _) in read_atoms(ap, args, false)? {
+                    self.state.unset(atom);
+                }
+            },
+            "reset" => self.state = problem.start,
+            "actions" => {
+                for (action, cost, next) in StateTransIter::new(&self.state, ap) {
+                    let changes = changes(ap, &self.state, &next);
+                    writeln!(out, "  {} (cost {}): {}", ap.name(action), cost, changes.join(" "))?;
+                }
+            },
+            "apply" => {
+                let name = match args {
+                    [name] => name,
+                    _ => return Err("apply needs one action".into())
+                };
+                let action = ap.lookup_action(name).ok_or_else(|| format!("unknown action `{}`", name))?;
+                if !ap.is_applicable(action, &self.state) {
+                    return Err(format!("`{}` is not applicable", name).into());
+                }
+                let next = ap.apply(action, &self.state);
+                writeln!(out, "{}", changes(ap, &self.state, &next).join(" "))?;
+                self.state = next;
+            },
+            "plan" => {
+                let mut goal = problem.goal;
+                if !args.is_empty() {
+                    goal = WorldState::new();
+                    set_atoms(ap, &mut goal, args, true)?;
+                }
+                match self.astar.plan_or_explain(ap, &self.state, &goal) {
+                    Ok(plan) => {
+                        writeln!(out, "Plan (cost {}):", plan.cost())?;
+                        for (i, (action, _)) in plan.iter().skip(1).enumerate() {
+                            writeln!(out, "  {}. {}", i + 1, action)?;
+                        }
+                    },
+                    Err(diagnosis) => write!(out, "No plan found.\n{}", diagnosis.display(ap))?
+                }
+            },
+            _ => return Err(format!("unknown command `{}`; try `help`", command).into())
+        }
+        Ok(())
+    }
+
+    /// Reads commands from `input` until it ends or the user quits.
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, out: &mut W) -> io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            write!(out, "> ")?;
+            out.flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return writeln!(out)
+            };
+            match self.execute(&line, out) {
+                Ok(true) => {},
+                Ok(false) => return Ok(()),
+                Err(err) => writeln!(out, "error: {}", err)?
+            }
+        }
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{split_words, Session};
+    use gpgoap::parse_dsl;
+
+    const COMBAT: &str = "
+        action load { pre armedwithgun; post weaponloaded }
+        action shoot { pre weaponloaded; post !enemyalive }
+        start { armedwithgun, !weaponloaded, enemyalive }
+        goal { !enemyalive }
+    ";
+
+    fn session() -> Session {
+        let mut session = Session::new();
+        let problem = parse_dsl(COMBAT).unwrap();
+        session.state = problem.start;
+        session.problem = Some(problem);
+        session
+    }
+
+    fn run(session: &mut Session, line: &str) -> Result<String, String> {
+        let mut out = Vec::new();
+        session.execute(line, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn steps_through_a_problem() {
+        let mut session = session();
+        assert_eq!("  load (cost 1): +weaponloaded\n", run(&mut session, "actions").unwrap());
+        assert!(run(&mut session, "apply shoot").unwrap_err().contains("not applicable"));
+        assert_eq!("+weaponloaded\n", run(&mut session, "apply load").unwrap());
+        assert_eq!("Plan (cost 1):\n  1. shoot\n", run(&mut session, "plan").unwrap());
+
+        run(&mut session, "clear armedwithgun weaponloaded").unwrap();
+        assert!(run(&mut session, "plan").unwrap().starts_with("No plan found.\nNever applicable: load, shoot\n"));
+        run(&mut session, "reset").unwrap();
+        assert_eq!("Plan (cost 1):\n  1. load\n", run(&mut session, "plan weaponloaded").unwrap());
+        assert!(run(&mut session, "set laoded").unwrap_err().contains("unknown atom `laoded`"));
+    }
+
+    #[test]
+    fn rejects_bad_commands_whole() {
+        let mut session = session();
+        let before = run(&mut session, "state").unwrap();
+        assert!(run(&mut session, "set weaponloaded laoded").is_err());
+        assert!(run(&mut session, "clear armedwithgun laoded").is_err());
+        assert_eq!(before, run(&mut session, "state").unwrap());
+
+        run(&mut session, "clear armedwithgun").unwrap();
+        assert_eq!("-weaponloaded\n+enemyalive\n\n", run(&mut session, "state").unwrap());
+    }
+
+    #[test]
+    fn splits_quoted_words() {
+        assert_eq!(vec!["set", "!on(a, b)", "clear"], split_words("set !\"on(a, b)\"  clear").unwrap());
+        assert!(split_words("set \"on(a").is_err());
+        assert!(Session::new().execute("state", &mut Vec::new()).unwrap_err().contains("no problem"));
+    }
+}
@@ -1,7 +1,6 @@
 use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
-use std::u64;
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub struct BitSetU64 {
     bits: u64
 }
@@ -17,7 +16,7 @@ impl BitSetU64 {
     }
 
     pub fn new_with(bits: u64) -> Self {
-        BitSetU64{bits: bits}
+        BitSetU64{bits}
     }
 
     #[inline]
@@ -52,6 +51,11 @@ impl BitSetU64 {
         (self.bits & mask) != 0
     }
 
+    #[inline]
+    pub fn bits(&self) -> u64 {
+        self.bits
+    }
+
     #[inline]
     pub fn count_ones(&self) -> u32 {
         self.bits.count_ones()
@@ -125,21 +129,21 @@ mod tests {
         let full = BitSetU64::full();
         let mut march = BitSetU64::empty();
         for i in 0..64 {
-            assert!(empty.get(i) != true);
-            assert!(full.get(i) == true);
+            assert!(!empty.get(i));
+            assert!(full.get(i));
             march.enable(i);
-            assert!(march.get(i) == true);
+            assert!(march.get(i));
             for j in 0..i {
-                assert!(march.get(j) == false);
+                assert!(!march.get(j));
             }
             for j in i+1..64 {
-                assert!(march.get(j) == false);
+                assert!(!march.get(j));
             }
             march.disable(i);
         }
         march.set(1, true);
-        assert!(full.get(1) == true);
+        assert!(full.get(1));
         march.set(1, false);
-        assert!(empty.get(1) != true);
+        assert!(!empty.get(1));
     }
 }
\ No newline at end of file
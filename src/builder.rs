@@ -0,0 +1,248 @@
+use ::goap::{WorldState, ActionPlanner, ActionPlannerBuilder, ActionId, MAX_ATOMS};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BuildError {
+    /// An action with this name was already defined.
+    DuplicateAction(String),
+    /// The same atom was given both `true` and `false`.
+    Conflict(String),
+    /// A state refers to an atom the planner does not know.
+    UnknownAtom(String),
+    /// An action was given a cost below zero.
+    NegativeCost { action: String, cost: i32 },
+    /// Defining the action would need more atoms than an `ActionPlanner` can hold.
+    TooManyAtoms
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuildError::DuplicateAction(ref action) => write!(f, "action `{}` is already defined", action),
+            BuildError::Conflict(ref atom) => write!(f, "atom `{}` is both required true and false", atom),
+            BuildError::UnknownAtom(ref atom) => write!(f, "unknown atom `{}`", atom),
+            BuildError::NegativeCost { ref action, cost } =>
+                write!(f, "action `{}` has negative cost {}", action, cost),
+            BuildError::TooManyAtoms => write!(f, "domain has more than the maximum number of atoms")
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+/// Records `atom = value` unless `atom` was already given the opposite value.
+fn push_value(values: &mut Vec<(String, bool)>, atom: &str, value: bool) -> Result<(), BuildError> {
+    match values.iter().find(|(name, _)| name == atom) {
+        Some(&(_, old)) if old != value => Err(BuildError::Conflict(atom.to_owned())),
+        Some(_) => Ok(()),
+        None => {
+            values.push((atom.to_owned(), value));
+            Ok(())
+        }
+    }
+}
+
+/// Defines one action of an `ActionPlannerBuilder`, returned by `ActionPlannerBuilder::action`.
+///
+/// Nothing is registered until `build`, which checks the whole definition first and leaves
+/// the planner untouched if it is rejected.
+pub struct ActionBuilder<'a> {
+    ap: &'a mut ActionPlannerBuilder,
+    name: String,
+    cost: i32,
+    pre: Vec<(String, bool)>,
+    post: Vec<(String, bool)>,
+    error: Option<BuildError>
+}
+
+impl<'a> ActionBuilder<'a> {
+    pub fn cost(mut self, cost: i32) -> Self {
+        self.cost = cost;
+        self
+    }
+
+    /// Requires `atom` to have `value` before the action can run.
+    pub fn pre(mut self, atom: &str, value: bool) -> Self {
+        if self.error.is_none() {
+            self.error = push_value(&mut self.pre, atom, value).err();
+        }
+        self
+    }
+
+    /// Makes the action set `atom` to `value`.
+    pub fn post(mut self, atom: &str, value: bool) -> Self {
+        if self.error.is_none() {
+            self.error = push_value(&mut self.post, atom, value).err();
+        }
+        self
+    }
+
+    pub fn requires(self, atom: &str) -> Self {
+        self.pre(atom, true)
+    }
+
+    pub fn forbids(self, atom: &str) -> Self {
+        self.pre(atom, false)
+    }
+
+    pub fn sets(self, atom: &str) -> Self {
+        self.post(atom, true)
+    }
+
+    pub fn clears(self, atom: &str) -> Self {
+        self.post(atom, false)
+    }
+
+    /// Registers the action and any new atoms it mentions.
+    pub fn build(self) -> Result<ActionId, BuildError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if self.ap.lookup_action(&self.name).is_some() {
+            return Err(BuildError::DuplicateAction(self.name));
+        }
+        if self.cost < 0 {
+            return Err(BuildError::NegativeCost { action: self.name, cost: self.cost });
+        }
+        let mut new_atoms: Vec<&str> = Vec::new();
+        for (atom, _) in self.pre.iter().chain(&self.post) {
+            if self.ap.lookup_atom(atom).is_none() && !new_atoms.contains(&&**atom) {
+                new_atoms.push(atom);
+            }
+        }
+        if self.ap.atom_names().len() + new_atoms.len() > MAX_ATOMS {
+            return Err(BuildError::TooManyAtoms);
+        }
+
+        let action = self.ap.register_action(&self.name).unwrap();
+        self.ap.set_cost_id(action, self.cost);
+        for (atom, value) in &self.pre {
+            let atom = self.ap.register_atom(atom).unwrap();
+            self.ap.set_pre_id(action, atom, *value);
+        }
+        for (atom, value) in &self.post {
+            let atom = self.ap.register_atom(atom).unwrap();
+            self.ap.set_post_id(action, atom, *value);
+        }
+        Ok(action)
+    }
+}
+
+impl ActionPlannerBuilder {
+    /// Starts defining the action `name`, e.g.
+    /// `ap.action("aim").cost(2).requires("enemyvisible").sets("enemylinedup").build()?`.
+    pub fn action<'a>(&'a mut self, name: &str) -> ActionBuilder<'a> {
+        ActionBuilder {
+            ap: self,
+            name: name.to_owned(),
+            cost: 1,
+            pre: Vec::new(),
+            post: Vec::new(),
+            error: None
+        }
+    }
+}
+
+/// Builds a `WorldState` over the atoms of an `ActionPlanner`, returned by
+/// `WorldState::builder`. Unknown atoms are reported by `build` instead of being ignored.
+pub struct WorldStateBuilder<'a> {
+    ap: &'a ActionPlanner,
+    values: Vec<(String, bool)>,
+    error: Option<BuildError>
+}
+
+impl<'a> WorldStateBuilder<'a> {
+    pub fn with(mut self, atom: &str, value: bool) -> Self {
+        if self.error.is_none() {
+            self.error = push_value(&mut self.values, atom, value).err();
+        }
+        self
+    }
+
+    pub fn holds(self, atom: &str) -> Self {
+        self.with(atom, true)
+    }
+
+    pub fn lacks(self, atom: &str) -> Self {
+        self.with(atom, false)
+    }
+
+    pub fn build(self) -> Result<WorldState, BuildError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let mut ws = WorldState::new();
+        for (atom, value) in self.values {
+            if !ws.set(self.ap, &atom, value) {
+                return Err(BuildError::UnknownAtom(atom));
+            }
+        }
+        Ok(ws)
+    }
+}
+
+impl WorldState {
+    /// Starts building a state over the atoms of `ap`, e.g.
+    /// `WorldState::builder(&ap).holds("alive").lacks("enemyvisible").build()?`.
+    pub fn builder<'a>(ap: &'a ActionPlanner) -> WorldStateBuilder<'a> {
+        WorldStateBuilder { ap, values: Vec::new(), error: None }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::BuildError;
+    use ::goap::{ActionPlanner, WorldState};
+    use ::astar::AStar;
+
+    #[test]
+    fn builds_actions_and_states() {
+        let mut builder = ActionPlanner::builder();
+        builder.action("load").requires("armedwithgun").sets("weaponloaded").build().unwrap();
+        let shoot = builder.action("shoot").cost(2)
+            .requires("weaponloaded")
+            .clears("enemyalive")
+            .build().unwrap();
+        let ap = builder.build();
+        assert_eq!(2, ap.cost(shoot));
+
+        let start = WorldState::builder(&ap)
+            .holds("armedwithgun")
+            .lacks("weaponloaded")
+            .holds("enemyalive")
+            .build().unwrap();
+        let goal = WorldState::builder(&ap).lacks("enemyalive").build().unwrap();
+        let plan = AStar::new().plan(&ap, &start, &goal).unwrap();
+        assert_eq!(3, plan.cost());
+    }
+
+    #[test]
+    fn rejects_bad_definitions() {
+        let mut builder = ActionPlanner::builder();
+        builder.action("load").sets("weaponloaded").build().unwrap();
+        assert_eq!(Err(BuildError::DuplicateAction("load".to_owned())),
+                   builder.action("load").build());
+        assert_eq!(Err(BuildError::Conflict("alive".to_owned())),
+                   builder.action("flee").requires("alive").forbids("alive").build());
+        assert_eq!(None, builder.lookup_action("flee"));
+        assert_eq!(None, builder.lookup_atom("alive"));
+
+        let ap = builder.build();
+        assert_eq!(Err(BuildError::UnknownAtom("weaponlaoded".to_owned())),
+                   WorldState::builder(&ap).holds("weaponlaoded").build());
+    }
+
+    #[test]
+    fn rejects_too_many_atoms() {
+        let mut builder = ActionPlanner::builder();
+        for i in 0..63 {
+            builder.register_atom(&format!("atom_{}", i));
+        }
+        assert_eq!(Err(BuildError::TooManyAtoms), builder.action("a").sets("x").sets("y").build());
+        assert_eq!(None, builder.lookup_action("a"));
+        assert!(builder.action("a").sets("x").build().is_ok());
+    }
+}
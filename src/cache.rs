@@ -0,0 +1,184 @@
+use ::goap::{WorldState, ActionPlanner};
+use ::astar::{AStar, AStarPlan, Heuristic};
+use ::graph::state_key;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+struct CacheKey {
+    version: u64,
+    start: (u64, u64),
+    goal: (u64, u64),
+    heuristic: Heuristic,
+    greedy: bool
+}
+
+/// Remembers the results of recent `AStar::plan` calls so that repeated queries skip the
+/// search.
+///
+/// Entries are keyed by the planner's `version`, the start and the goal, along with the
+/// search's heuristic and greediness. Rebuilding a planner gives it a new version, so plans
+/// for the old domain are never returned for the new one; they simply age out. Failed
+/// searches are remembered too. When the cache is full the least recently used entry is
+/// dropped.
+pub struct PlanCache {
+    capacity: usize,
+    entries: HashMap<CacheKey, (Option<AStarPlan>, u64)>,
+    /// Each entry's key by the clock value of its last use, oldest first.
+    recency: BTreeMap<u64, CacheKey>,
+    clock: u64,
+    hits: u64,
+    misses: u64
+}
+
+impl PlanCache {
+    /// A cache holding at most `capacity` results.
+    pub fn new(capacity: usize) -> Self {
+        PlanCache {
+            capacity,
+            entries: HashMap::with_capacity(capacity),
+            recency: BTreeMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0
+        }
+    }
+
+    /// Returns the cached result for this query, or plans with `astar` and caches that.
+    pub fn plan(&mut self, astar: &mut AStar, ap: &ActionPlanner, start: &WorldState, goal: &WorldState)
+                -> Option<AStarPlan> {
+        let key = CacheKey {
+            version: ap.version(),
+            start: state_key(start),
+            goal: state_key(goal),
+            heuristic: astar.heuristic(),
+            greedy: astar.greedy()
+        };
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(&key) {
+            self.recency.remove(&entry.1);
+            self.recency.insert(self.clock, key);
+            entry.1 = self.clock;
+            self.hits += 1;
+            return entry.0.clone();
+        }
+
+        self.misses += 1;
+        let plan = astar.plan(ap, start, goal);
+        if self.capacity == 0 {
+            return plan;
+        }
+        if self.entries.len() == self.capacity {
+            let (_, oldest) = self.recency.pop_first().unwrap();
+            self.entries.remove(&oldest);
+        }
+        self.entries.insert(key, (plan.clone(), self.clock));
+        self.recency.insert(self.clock, key);
+        plan
+    }
+
+    /// Queries answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Queries that had to search.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Forgets every cached result. The hit and miss counts are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::PlanCache;
+    use ::goap::{ActionPlanner, WorldState};
+    use ::astar::AStar;
+    use ::test_domains::{gunner, enemy_dead};
+
+    fn planner() -> ActionPlanner {
+        let mut builder = ActionPlanner::builder();
+        gunner(&mut builder);
+        builder.build()
+    }
+
+    #[test]
+    fn caches_repeated_queries() {
+        let ap = planner();
+        let start = WorldState::builder(&ap).holds("armedwithgun").lacks("weaponloaded").holds("enemyalive")
+            .build().unwrap();
+        let goal = enemy_dead(&ap);
+        let mut astar = AStar::new();
+        let mut cache = PlanCache::new(2);
+
+        let first = cache.plan(&mut astar, &ap, &start, &goal).unwrap();
+        let second = cache.plan(&mut astar, &ap, &start, &goal).unwrap();
+        assert!(first.iter().eq(second.iter()));
+        assert_eq!((1, 1), (cache.hits(), cache.misses()));
+
+        // Failures are cached as well.
+        let unarmed = WorldState::builder(&ap).lacks("armedwithgun").lacks("weaponloaded").holds("enemyalive")
+            .build().unwrap();
+        assert!(cache.plan(&mut astar, &ap, &unarmed, &goal).is_none());
+        assert!(cache.plan(&mut astar, &ap, &unarmed, &goal).is_none());
+        assert_eq!((2, 2), (cache.hits(), cache.misses()));
+
+        astar.set_greedy(true);
+        cache.plan(&mut astar, &ap, &start, &goal).unwrap();
+        assert_eq!(3, cache.misses());
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let ap = planner();
+        let goal = enemy_dead(&ap);
+        let starts: Vec<WorldState> = ["armedwithgun", "weaponloaded", "enemyalive"].iter()
+            .map(|name| WorldState::builder(&ap).holds(name).build().unwrap())
+            .collect();
+        let mut astar = AStar::new();
+        let mut cache = PlanCache::new(2);
+
+        cache.plan(&mut astar, &ap, &starts[0], &goal);
+        cache.plan(&mut astar, &ap, &starts[1], &goal);
+        cache.plan(&mut astar, &ap, &starts[0], &goal);
+        cache.plan(&mut astar, &ap, &starts[2], &goal);
+        assert_eq!(2, cache.len());
+        cache.plan(&mut astar, &ap, &starts[0], &goal);
+        assert_eq!((2, 3), (cache.hits(), cache.misses()));
+        cache.plan(&mut astar, &ap, &starts[1], &goal);
+        assert_eq!((2, 4), (cache.hits(), cache.misses()));
+    }
+
+    #[test]
+    fn rebuilt_planners_miss() {
+        let ap = planner();
+        let start = WorldState::builder(&ap).holds("armedwithgun").lacks("weaponloaded").holds("enemyalive")
+            .build().unwrap();
+        let goal = enemy_dead(&ap);
+        let mut astar = AStar::new();
+        let mut cache = PlanCache::new(4);
+        assert_eq!(3, cache.plan(&mut astar, &ap, &start, &goal).unwrap().cost());
+
+        let version = ap.version();
+        let mut builder = ap.into_builder();
+        builder.set_cost("shoot", 5);
+        let ap = builder.build();
+        assert!(ap.version() != version);
+        assert_eq!(6, cache.plan(&mut astar, &ap, &start, &goal).unwrap().cost());
+        assert_eq!((0, 2), (cache.hits(), cache.misses()));
+    }
+}
@@ -0,0 +1,145 @@
+use ::goap::{WorldState, ActionPlanner, AtomId, ActionId};
+use ::astar::{AStar, AStarPlan, StateTransIter, mismatch};
+use std::fmt;
+
+/// Why `AStar::plan_or_explain` found no plan.
+///
+/// The lists describe the states the search explored. If it stopped at its node limit
+/// (`overflowed`) these are only part of the reachable states.
+#[derive(Debug)]
+pub struct Diagnosis {
+    /// Goal atoms the start gets wrong and no action's `post` sets to the goal value.
+    pub unachievable: Vec<AtomId>,
+    /// Actions whose preconditions no explored state meets.
+    pub never_applicable: Vec<ActionId>,
+    /// The plan to the explored state with the fewest goal atoms wrong, which is its last
+    /// entry.
+    pub closest: AStarPlan,
+    /// The goal atoms the closest state gets wrong.
+    pub missing: Vec<AtomId>,
+    /// Whether the search gave up at its node limit.
+    pub overflowed: bool
+}
+
+impl AStar {
+    /// Plans like `plan`, but explains a failure with a `Diagnosis`.
+    pub fn plan_or_explain(&mut self, ap: &ActionPlanner, start: &WorldState, goal: &WorldState)
+                           -> Result<AStarPlan, Diagnosis> {
+        if let Some(plan) = self.plan(ap, start, goal) {
+            return Ok(plan);
+        }
+
+        let wrong = mismatch(start, goal);
+        let unachievable = ap.atom_ids()
+            .filter(|atom| wrong.get(atom.index()))
+            .filter(|atom| !ap.action_ids().any(|action| {
+                let post = ap.post(action);
+                !post.dontcare.get(atom.index()) && post.values.get(atom.index()) == goal.values.get(atom.index())
+            }))
+            .collect();
+
+        let explored: Vec<_> = self.closed.iter().chain(&self.opened).collect();
+        let mut applicable = vec![false; ap.action_ids().count()];
+        for node in &explored {
+            for (action, _, _) in StateTransIter::new(&node.ws, ap) {
+                applicable[action.index()] = true;
+            }
+        }
+        let never_applicable = ap.action_ids().filter(|action| !applicable[action.index()]).collect();
+
+        // The start is always explored, so there is a closest node.
+        let closest = explored.iter()
+            .min_by_key(|node| (mismatch(&node.ws, goal).count_ones(), node.g))
+            .unwrap();
+        let wrong = mismatch(&closest.ws, goal);
+        Err(Diagnosis {
+            unachievable,
+            never_applicable,
+            closest: self.reconstruct_plan(ap, closest),
+            missing: ap.atom_ids().filter(|atom| wrong.get(atom.index())).collect(),
+            overflowed: self.stats().overflowed
+        })
+    }
+}
+
+impl Diagnosis {
+    pub fn display<'a, 'b>(&'a self, ap: &'b ActionPlanner) -> DiagnosisFmt<'a, 'b> {
+        DiagnosisFmt { diagnosis: self, ap }
+    }
+}
+
+/// Writes a `Diagnosis` with atoms and actions by name.
+pub struct DiagnosisFmt<'a, 'b> {
+    diagnosis: &'a Diagnosis,
+    ap: &'b ActionPlanner
+}
+
+impl<'a, 'b> fmt::Display for DiagnosisFmt<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = self.diagnosis;
+        let ap = self.ap;
+        let atoms = |atoms: &[AtomId]| atoms.iter().map(|&atom| &**ap.atom_name(atom)).collect::<Vec<_>>().join(", ");
+        if d.overflowed {
+            writeln!(f, "The search hit its node limit, so only part of the state space was explored.")?;
+        }
+        if !d.unachievable.is_empty() {
+            writeln!(f, "No action achieves goal atoms: {}", atoms(&d.unachievable))?;
+        }
+        if !d.never_applicable.is_empty() {
+            let names: Vec<&str> = d.never_applicable.iter().map(|&action| &**ap.name(action)).collect();
+            writeln!(f, "Never applicable: {}", names.join(", "))?;
+        }
+        let steps: Vec<&str> = d.closest.iter().skip(1).map(|(action, _)| &**action).collect();
+        if steps.is_empty() {
+            write!(f, "Closest state is the start")?;
+        } else {
+            write!(f, "Closest state reached by {}", steps.join(", "))?;
+        }
+        writeln!(f, " (cost {}), still missing: {}", d.closest.cost(), atoms(&d.missing))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use ::goap::{ActionPlanner, WorldState};
+    use ::astar::AStar;
+
+    #[test]
+    fn explains_missing_plan() {
+        let mut builder = ActionPlanner::builder();
+        builder.action("load").requires("armedwithgun").sets("weaponloaded").build().unwrap();
+        builder.action("aim").requires("weaponloaded").requires("enemyvisible").sets("enemylinedup").build().unwrap();
+        builder.action("shoot").requires("enemylinedup").clears("enemyalive").build().unwrap();
+        builder.register_atom("alive");
+        let ap = builder.build();
+        let start = WorldState::builder(&ap)
+            .holds("armedwithgun").lacks("weaponloaded").lacks("enemyvisible").lacks("enemylinedup")
+            .holds("enemyalive").lacks("alive")
+            .build().unwrap();
+        let goal = WorldState::builder(&ap).lacks("enemyalive").holds("alive").build().unwrap();
+
+        let diagnosis = AStar::new().plan_or_explain(&ap, &start, &goal).unwrap_err();
+        assert_eq!(vec![ap.lookup_atom("alive").unwrap()], diagnosis.unachievable);
+        let never: Vec<&str> = diagnosis.never_applicable.iter().map(|&a| &**ap.name(a)).collect();
+        assert_eq!(vec!["aim", "shoot"], never);
+        assert!(!diagnosis.overflowed);
+        assert_eq!(2, diagnosis.missing.len());
+
+        let text = diagnosis.display(&ap).to_string();
+        assert!(text.contains("No action achieves goal atoms: alive\n"));
+        assert!(text.contains("Never applicable: aim, shoot\n"));
+        assert!(text.contains("Closest state is the start (cost 0), still missing: enemyalive, alive\n"));
+    }
+
+    #[test]
+    fn passes_plans_through() {
+        let mut builder = ActionPlanner::builder();
+        builder.action("load").sets("weaponloaded").build().unwrap();
+        let ap = builder.build();
+        let start = WorldState::builder(&ap).lacks("weaponloaded").build().unwrap();
+        let goal = WorldState::builder(&ap).holds("weaponloaded").build().unwrap();
+        assert_eq!(1, AStar::new().plan_or_explain(&ap, &start, &goal).unwrap().cost());
+    }
+}
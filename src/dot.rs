@@ -0,0 +1,134 @@
+use ::goap::{WorldState, ActionPlanner};
+use ::astar::{AStar, AStarNode, AStarPlan};
+use ::graph::StateGraph;
+use std::fmt::Write;
+
+/// Escapes `text` for use inside a double-quoted DOT string.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+/// One `+name` or `-name` line per atom `ws` cares about.
+pub(crate) fn state_lines(ap: &ActionPlanner, ws: &WorldState) -> String {
+    let mut lines = String::new();
+    for atom in ap.atom_ids().filter(|atom| !ws.dontcare.get(atom.index())) {
+        let sign = if ws.values.get(atom.index()) { '+' } else { '-' };
+        writeln!(lines, "{}{}", sign, ap.atom_name(atom)).unwrap();
+    }
+    lines
+}
+
+/// Writes the nodes the last `AStar::plan` call generated as a Graphviz digraph. Expanded
+/// nodes are drawn solid and nodes still on the open list dashed; each node is labelled with
+/// its cost so far `g`, heuristic `h` and state. Edges lead from a node's parent and are
+/// labelled with the action and its cost. When `plan` is given its states and steps are
+/// drawn in red.
+pub fn write_search_dot(astar: &AStar, ap: &ActionPlanner, plan: Option<&AStarPlan>) -> String {
+    let nodes: Vec<(&AStarNode, bool)> = astar.closed.iter().map(|node| (node, false))
+        .chain(astar.opened.iter().map(|node| (node, true)))
+        .collect();
+    let find = |ws: &WorldState| nodes.iter().position(|(node, _)| node.ws.values == ws.values);
+    let plan_states: Vec<&WorldState> = plan.iter().flat_map(|plan| plan.iter().map(|(_, ws)| ws)).collect();
+    let in_plan = |ws: &WorldState| plan_states.iter().any(|step| step.values == ws.values);
+    let plan_step = |from: &WorldState, to: &WorldState| plan_states.windows(2)
+        .any(|pair| pair[0].values == from.values && pair[1].values == to.values);
+
+    let mut out = String::new();
+    writeln!(out, "digraph search {{").unwrap();
+    writeln!(out, "  node [shape=box];").unwrap();
+    for (i, &(node, open)) in nodes.iter().enumerate() {
+        let label = format!("g={} h={}\n{}", node.g, node.h, state_lines(ap, &node.ws));
+        let mut attrs = format!("label=\"{}\"", escape(&label));
+        if open {
+            attrs.push_str(", style=dashed");
+        }
+        if in_plan(&node.ws) {
+            attrs.push_str(", color=red, penwidth=2");
+        }
+        writeln!(out, "  n{} [{}];", i, attrs).unwrap();
+    }
+    for (i, &(node, _)) in nodes.iter().enumerate() {
+        let (parentws, action) = match (node.parentws, node.action) {
+            (Some(parentws), Some(action)) => (parentws, action),
+            _ => continue
+        };
+        let parent = match find(&parentws) {
+            Some(parent) => parent,
+            None => continue
+        };
+        let label = format!("{} ({})", ap.name(action), ap.cost(action));
+        let mut attrs = format!("label=\"{}\"", escape(&label));
+        if plan_step(&parentws, &node.ws) {
+            attrs.push_str(", color=red, penwidth=2");
+        }
+        writeln!(out, "  n{} -> n{} [{}];", parent, i, attrs).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// Writes a `StateGraph` as a Graphviz digraph, with the start state drawn with a double
+/// border and each transition labelled with its action and cost.
+pub fn write_state_graph_dot(graph: &StateGraph, ap: &ActionPlanner) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph states {{").unwrap();
+    writeln!(out, "  node [shape=box];").unwrap();
+    for (i, ws) in graph.states.iter().enumerate() {
+        let border = if i == 0 { ", peripheries=2" } else { "" };
+        writeln!(out, "  n{} [label=\"{}\"{}];", i, escape(&state_lines(ap, ws)), border).unwrap();
+    }
+    for t in &graph.transitions {
+        let label = format!("{} ({})", ap.name(t.action), t.cost);
+        writeln!(out, "  n{} -> n{} [label=\"{}\"];", t.from, t.to, escape(&label)).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{escape, write_search_dot};
+    use ::goap::{ActionPlanner, WorldState};
+    use ::astar::AStar;
+
+    #[test]
+    fn writes_explored_nodes() {
+        let mut builder = ActionPlanner::builder();
+        builder.action("load").requires("armedwithgun").sets("weaponloaded").build().unwrap();
+        builder.action("shoot").cost(2).requires("weaponloaded").clears("enemyalive").build().unwrap();
+        builder.action("flee").sets("far").build().unwrap();
+        let ap = builder.build();
+        let start = WorldState::builder(&ap)
+            .holds("armedwithgun").lacks("weaponloaded").holds("enemyalive").lacks("far")
+            .build().unwrap();
+        let goal = WorldState::builder(&ap).lacks("enemyalive").build().unwrap();
+
+        let mut astar = AStar::new();
+        let plan = astar.plan(&ap, &start, &goal).unwrap();
+        let dot = write_search_dot(&astar, &ap, Some(&plan));
+        assert!(dot.starts_with("digraph search {\n"));
+        assert!(dot.contains("label=\"load (1)\", color=red"));
+        assert!(dot.contains("label=\"shoot (2)\", color=red"));
+        assert!(dot.contains("label=\"flee (1)\"];"));
+        assert!(dot.contains("g=3 h=0\\n+armedwithgun\\n+weaponloaded\\n-enemyalive\\n-far\\n\", color=red"));
+        assert_eq!(astar.stats().generated, dot.matches("label=\"g=").count());
+    }
+
+    #[test]
+    fn escapes_labels() {
+        assert_eq!("on(\\\"a\\\", b)\\nx\\\\y", escape("on(\"a\", b)\nx\\y"));
+    }
+}
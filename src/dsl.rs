@@ -0,0 +1,479 @@
+//! A small text format for planning problems:
+//!
+//! ```text
+//! # Atoms are registered in order of first use unless declared up front.
+//! atoms { alive }
+//!
+//! action aim cost 1 { pre enemyvisible, weaponloaded; post enemylinedup }
+//! action shoot { pre enemylinedup; post !enemyalive }
+//!
+//! start { enemyvisible, weaponloaded, enemyalive, alive }
+//! goal { !enemyalive, alive }
+//! ```
+//!
+//! `!name` sets an atom to false. Names that are not plain identifiers, such as grounded
+//! atoms like `on(a, b)`, are written in double quotes.
+
+use ::goap::{ActionPlanner, ActionPlannerBuilder, WorldState, Problem};
+use std::error::Error;
+use std::fmt;
+
+const KEYWORDS: [&str; 7] = ["atoms", "action", "cost", "pre", "post", "start", "goal"];
+
+/// A region of the source text. `start` and `end` are byte offsets; `line` and `column` are
+/// the 1-based position of `start`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DslError {
+    pub span: Span,
+    pub message: String
+}
+
+impl fmt::Display for DslError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.span.line, self.span.column, self.message)
+    }
+}
+
+impl Error for DslError {}
+
+#[derive(PartialEq, Clone)]
+enum Token {
+    Name(String),
+    Quoted(String),
+    Punct(char),
+    End
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize
+}
+
+impl<'a> Lexer<'a> {
+    fn peek_char(&self) -> Option<char> {
+        self.src[self.offset..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek_char()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn span_from(&self, start: Span) -> Span {
+        Span { end: self.offset, ..start }
+    }
+
+    fn next_token(&mut self) -> Result<(Token, Span), DslError> {
+        loop {
+            match self.peek_char() {
+                Some('#') => while self.peek_char().is_some_and(|c| c != '\n') {
+                    self.bump();
+                },
+                Some(c) if c.is_whitespace() => { self.bump(); },
+                _ => break
+            }
+        }
+        let start = Span { start: self.offset, end: self.offset, line: self.line, column: self.column };
+        let c = match self.bump() {
+            Some(c) => c,
+            None => return Ok((Token::End, start))
+        };
+        match c {
+            '{' | '}' | ',' | ';' | '!' => Ok((Token::Punct(c), self.span_from(start))),
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match self.bump() {
+                        Some('"') => return Ok((Token::Quoted(value), self.span_from(start))),
+                        Some('\\') => match self.bump() {
+                            Some(escaped @ '"') | Some(escaped @ '\\') => value.push(escaped),
+                            _ => return Err(DslError {
+                                span: self.span_from(start),
+                                message: "unknown escape in string".to_owned()
+                            })
+                        },
+                        Some(c) => value.push(c),
+                        None => return Err(DslError {
+                            span: self.span_from(start),
+                            message: "unterminated string".to_owned()
+                        })
+                    }
+                }
+            },
+            c if is_name_char(c) => {
+                let mut value = c.to_string();
+                while let Some(c) = self.peek_char().filter(|&c| is_name_char(c)) {
+                    value.push(c);
+                    self.bump();
+                }
+                Ok((Token::Name(value), self.span_from(start)))
+            },
+            c => Err(DslError { span: self.span_from(start), message: format!("unexpected character `{}`", c) })
+        }
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    token: Token,
+    span: Span
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Result<Self, DslError> {
+        let mut lexer = Lexer { src, offset: 0, line: 1, column: 1 };
+        let (token, span) = lexer.next_token()?;
+        Ok(Parser { lexer, token, span })
+    }
+
+    fn advance(&mut self) -> Result<(Token, Span), DslError> {
+        let (token, span) = self.lexer.next_token()?;
+        let prev_token = ::std::mem::replace(&mut self.token, token);
+        let prev_span = ::std::mem::replace(&mut self.span, span);
+        Ok((prev_token, prev_span))
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, DslError> {
+        Err(DslError { span: self.span, message })
+    }
+
+    fn describe(&self) -> String {
+        match self.token {
+            Token::Name(ref n) => format!("`{}`", n),
+            Token::Quoted(ref n) => format!("\"{}\"", n),
+            Token::Punct(c) => format!("`{}`", c),
+            Token::End => "end of input".to_owned()
+        }
+    }
+
+    fn is_punct(&self, c: char) -> bool {
+        self.token == Token::Punct(c)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        match self.token {
+            Token::Name(ref n) => n == keyword,
+            _ => false
+        }
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<(), DslError> {
+        if self.is_punct(c) {
+            self.advance()?;
+            Ok(())
+        } else {
+            let found = self.describe();
+            self.error(format!("expected `{}`, found {}", c, found))
+        }
+    }
+
+    /// A plain or quoted name. Keywords are only names when quoted.
+    fn expect_name(&mut self, what: &str) -> Result<(String, Span), DslError> {
+        match self.token {
+            Token::Name(ref n) if !KEYWORDS.contains(&n.as_str()) => {},
+            Token::Quoted(_) => {},
+            _ => {
+                let found = self.describe();
+                return self.error(format!("expected {}, found {}", what, found));
+            }
+        }
+        match self.advance()? {
+            (Token::Name(n), span) | (Token::Quoted(n), span) => Ok((n, span)),
+            _ => unreachable!()
+        }
+    }
+
+    /// `[!]name (, [!]name)*`, stopping at `;` or `}`.
+    fn literals(&mut self) -> Result<Vec<(String, Span, bool)>, DslError> {
+        let mut literals = Vec::new();
+        if self.is_punct('}') || self.is_punct(';') {
+            return Ok(literals);
+        }
+        loop {
+            let value = !self.is_punct('!');
+            if !value {
+                self.advance()?;
+            }
+            let (name, span) = self.expect_name("an atom name")?;
+            literals.push((name, span, value));
+            if !self.is_punct(',') {
+                return Ok(literals);
+            }
+            self.advance()?;
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<(String, Span, bool)>, DslError> {
+        self.expect_punct('{')?;
+        let literals = self.literals()?;
+        self.expect_punct('}')?;
+        Ok(literals)
+    }
+
+    fn action(&mut self, builder: &mut ActionPlannerBuilder) -> Result<(), DslError> {
+        let (name, name_span) = self.expect_name("an action name")?;
+        if builder.lookup_action(&name).is_some() {
+            return Err(DslError { span: name_span, message: format!("duplicate action `{}`", name) });
+        }
+        builder.register_action(&name);
+        if self.is_keyword("cost") {
+            self.advance()?;
+            let cost = match self.token {
+                Token::Name(ref n) => n.parse::<i32>().ok(),
+                _ => None
+            };
+            match cost {
+                Some(cost) => builder.set_cost(&name, cost),
+                None => {
+                    let found = self.describe();
+                    return self.error(format!("expected an integer cost, found {}", found));
+                }
+            };
+            self.advance()?;
+        }
+
+        self.expect_punct('{')?;
+        while !self.is_punct('}') {
+            let is_pre = self.is_keyword("pre");
+            if !is_pre && !self.is_keyword("post") {
+                let found = self.describe();
+                return self.error(format!("expected `pre`, `post` or `}}`, found {}", found));
+            }
+            self.advance()?;
+            for (atom, span, value) in self.literals()? {
+                let set = match is_pre {
+                    true => builder.set_pre(&name, &atom, value),
+                    false => builder.set_post(&name, &atom, value)
+                };
+                if !set {
+                    return Err(too_many_atoms(span));
+                }
+            }
+            if self.is_punct(';') {
+                self.advance()?;
+            } else if !self.is_punct('}') {
+                let found = self.describe();
+                return self.error(format!("expected `;` or `}}`, found {}", found));
+            }
+        }
+        self.advance()?;
+        Ok(())
+    }
+}
+
+fn too_many_atoms(span: Span) -> DslError {
+    DslError { span, message: "too many atoms".to_owned() }
+}
+
+fn resolve(ap: &ActionPlanner, literals: &[(String, Span, bool)]) -> Result<WorldState, DslError> {
+    let mut ws = WorldState::new();
+    for &(ref atom, span, value) in literals {
+        if !ws.set(ap, atom, value) {
+            return Err(DslError { span, message: format!("unknown atom `{}`", atom) });
+        }
+    }
+    Ok(ws)
+}
+
+/// Parses a problem written in the text format described in the module documentation.
+///
+/// `start` and `goal` may only use atoms that are declared in `atoms` or used by an action.
+pub fn parse_dsl(src: &str) -> Result<Problem, DslError> {
+    let mut parser = Parser::new(src)?;
+    let mut builder = ActionPlanner::builder();
+    let mut start: Option<Vec<(String, Span, bool)>> = None;
+    let mut goal: Option<Vec<(String, Span, bool)>> = None;
+
+    while parser.token != Token::End {
+        let keyword = match parser.token {
+            Token::Name(ref n) if KEYWORDS.contains(&n.as_str()) => n.clone(),
+            _ => {
+                let found = parser.describe();
+                return parser.error(format!("expected `atoms`, `action`, `start` or `goal`, found {}", found));
+            }
+        };
+        let (_, keyword_span) = parser.advance()?;
+        match keyword.as_str() {
+            "atoms" => {
+                for (atom, span, value) in parser.block()? {
+                    if !value {
+                        return Err(DslError { span, message: "atom declarations cannot be negated".to_owned() });
+                    }
+                    builder.register_atom(&atom).ok_or_else(|| too_many_atoms(span))?;
+                }
+            },
+            "action" => parser.action(&mut builder)?,
+            "start" | "goal" => {
+                let slot = match keyword.as_str() {
+                    "start" => &mut start,
+                    _ => &mut goal
+                };
+                if slot.is_some() {
+                    return Err(DslError { span: keyword_span, message: format!("duplicate `{}`", keyword) });
+                }
+                *slot = Some(parser.block()?);
+            },
+            _ => return Err(DslError {
+                span: keyword_span,
+                message: format!("expected `atoms`, `action`, `start` or `goal`, found `{}`", keyword)
+            })
+        }
+    }
+
+    let planner = builder.build();
+    let start = resolve(&planner, &start.unwrap_or_default())?;
+    let goal = resolve(&planner, &goal.unwrap_or_default())?;
+    Ok(Problem { planner, start, goal })
+}
+
+fn write_name(out: &mut String, name: &str) {
+    let plain = !name.is_empty() && name.chars().all(is_name_char) && !KEYWORDS.contains(&name);
+    if plain {
+        out.push_str(name);
+    } else {
+        out.push('"');
+        for c in name.chars() {
+            if c == '"' || c == '\\' {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out.push('"');
+    }
+}
+
+fn write_literals(out: &mut String, ap: &ActionPlanner, ws: &WorldState) {
+    let atoms = ap.atom_ids().filter(|atom| !ws.dontcare.get(atom.index()));
+    for (i, atom) in atoms.enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        if !ws.values.get(atom.index()) {
+            out.push('!');
+        }
+        write_name(out, ap.atom_name(atom));
+    }
+}
+
+/// Writes `ap`, `start` and `goal` in the text format. Parsing the output with `parse_dsl`
+/// gives back the same atoms, actions and states.
+pub fn write_dsl(ap: &ActionPlanner, start: &WorldState, goal: &WorldState) -> String {
+    let mut out = String::new();
+    if !ap.atom_names().is_empty() {
+        out.push_str("atoms { ");
+        for (i, atom) in ap.atom_names().iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            write_name(&mut out, atom);
+        }
+        out.push_str(" }\n\n");
+    }
+
+    for action in ap.action_ids() {
+        out.push_str("action ");
+        write_name(&mut out, ap.name(action));
+        if ap.cost(action) != 1 {
+            out.push_str(&format!(" cost {}", ap.cost(action)));
+        }
+        out.push_str(" {\n");
+        for &(keyword, ws) in &[("pre", ap.pre(action)), ("post", ap.post(action))] {
+            if ws.dontcare != WorldState::new().dontcare {
+                out.push_str(&format!("    {} ", keyword));
+                write_literals(&mut out, ap, ws);
+                out.push_str(";\n");
+            }
+        }
+        out.push_str("}\n\n");
+    }
+
+    for &(keyword, ws) in &[("start", start), ("goal", goal)] {
+        out.push_str(keyword);
+        out.push_str(" { ");
+        write_literals(&mut out, ap, ws);
+        if ws.dontcare != WorldState::new().dontcare {
+            out.push(' ');
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{parse_dsl, write_dsl, DslError, Span};
+    use ::astar::AStar;
+    use ::test_domains::step_names;
+
+    const COMBAT: &str = "
+        # The scenario from examples/combat.goap, abridged.
+        atoms { alive }
+        action scout { pre armedwithgun; post enemyvisible }
+        action aim cost 2 { pre enemyvisible, weaponloaded; post enemylinedup }
+        action shoot { pre enemylinedup; post !enemyalive }
+        action load { pre armedwithgun; post weaponloaded }
+        action \"pick up(gun)\" { post armedwithgun; }
+
+        start { !enemyvisible, armedwithgun, !weaponloaded, enemyalive, alive }
+        goal { !enemyalive, alive }
+    ";
+
+    #[test]
+    fn parses_and_plans() {
+        let problem = parse_dsl(COMBAT).unwrap();
+        let ap = &problem.planner;
+        assert_eq!(Some(0), ap.lookup_atom("alive").map(|a| a.index()));
+        assert_eq!(2, ap.cost(ap.lookup_action("aim").unwrap()));
+        assert!(ap.lookup_action("pick up(gun)").is_some());
+
+        let plan = AStar::new().plan(ap, &problem.start, &problem.goal).unwrap();
+        assert_eq!(vec!["scout", "load", "aim", "shoot"], step_names(&plan));
+    }
+
+    #[test]
+    fn round_trips() {
+        let problem = parse_dsl(COMBAT).unwrap();
+        let text = write_dsl(&problem.planner, &problem.start, &problem.goal);
+        let again = parse_dsl(&text).unwrap();
+        assert_eq!(text, write_dsl(&again.planner, &again.start, &again.goal));
+        assert_eq!(problem.planner.atom_names(), again.planner.atom_names());
+        assert_eq!(problem.planner.act_pre(), again.planner.act_pre());
+        assert_eq!(problem.start, again.start);
+        assert_eq!(problem.goal, again.goal);
+    }
+
+    #[test]
+    fn reports_spans() {
+        let err = parse_dsl("action aim { pre visible }\ngoal { visible, alive }").unwrap_err();
+        assert_eq!(DslError {
+            span: Span { start: 43, end: 48, line: 2, column: 17 },
+            message: "unknown atom `alive`".to_owned()
+        }, err);
+
+        let err = parse_dsl("action aim { pre visible post linedup }").unwrap_err();
+        assert_eq!("1:26: expected `;` or `}`, found `post`", err.to_string());
+    }
+}
@@ -0,0 +1,187 @@
+use ::goap::{WorldState, ActionPlanner, ActionId};
+use ::astar::{AStar, AStarPlan};
+use ::repair::Repair;
+use std::sync::Arc;
+
+/// How the game reports the action it was told to run.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ActionOutcome {
+    Succeeded,
+    Failed
+}
+
+/// What a `PlanExecutor` makes of a report.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ExecStatus {
+    /// The world is as the plan expects and the next action can run.
+    Continue,
+    /// The world left the plan, so the executor mended it or planned again from the
+    /// observed state; see `PlanExecutor::last_repair`.
+    Replanned,
+    /// The goal holds.
+    Done,
+    /// The world left the plan and no plan reaches the goal from there.
+    Failed
+}
+
+/// Steps through an `AStarPlan` as the game runs its actions, replanning when the world
+/// stops matching the states the plan expects.
+pub struct PlanExecutor {
+    plan: AStarPlan,
+    goal: WorldState,
+    /// Index of the plan entry whose state the world is in; the next action is the one after.
+    step: usize,
+    state: WorldState,
+    astar: AStar,
+    replans: usize,
+    last_repair: Option<Repair>
+}
+
+impl PlanExecutor {
+    /// Starts executing `plan`, which should open at the current state and lead to `goal`.
+    pub fn new(plan: AStarPlan, goal: WorldState) -> Self {
+        let state = plan.iter().next().map_or_else(WorldState::new, |&(_, ws)| ws);
+        PlanExecutor { plan, goal, step: 0, state, astar: AStar::new(), replans: 0, last_repair: None }
+    }
+
+    /// The search used to replan, for setting its heuristic.
+    pub fn astar_mut(&mut self) -> &mut AStar {
+        &mut self.astar
+    }
+
+    /// The plan being executed. After replanning this is the new plan.
+    pub fn plan(&self) -> &AStarPlan {
+        &self.plan
+    }
+
+    /// Index into `plan()` of the entry the world is believed to be at.
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// The action to run next, or `None` when the plan is used up.
+    pub fn current(&self) -> Option<&Arc<str>> {
+        self.plan.entries.get(self.step + 1).map(|(name, _)| name)
+    }
+
+    /// The state the world is believed to be in: the plan's expectation, overlaid with
+    /// everything observed.
+    pub fn state(&self) -> &WorldState {
+        &self.state
+    }
+
+    /// How many times the executor has had to plan again.
+    pub fn replans(&self) -> usize {
+        self.replans
+    }
+
+    /// How the plan was last mended, or `None` if it never had to be.
+    pub fn last_repair(&self) -> Option<Repair> {
+        self.last_repair
+    }
+
+    /// Reports how the current action went and what the game observed afterwards. Atoms
+    /// `observed` leaves as don't-care keep their expected values.
+    ///
+    /// The executor replans from the observed state when the action failed, when `observed`
+    /// contradicts the state the plan expects, or when the next action can no longer apply.
+    /// It first tries to mend the rest of the plan with `AStar::repair`.
+    pub fn report(&mut self, ap: &ActionPlanner, outcome: ActionOutcome, observed: &WorldState) -> ExecStatus {
+        if outcome == ActionOutcome::Succeeded && self.current().is_some() {
+            self.step += 1;
+        }
+        let expected = self.plan.entries.get(self.step).map_or(self.state, |&(_, ws)| ws);
+        let diverged = observed.conflicts(&expected).next().is_some();
+        self.state = expected.overlay(observed);
+        if self.state.satisfies(&self.goal) {
+            return ExecStatus::Done;
+        }
+
+        let next_applies = self.next_action(ap).is_some_and(|action| ap.is_applicable(action, &self.state));
+        if outcome == ActionOutcome::Succeeded && !diverged && next_applies {
+            return ExecStatus::Continue;
+        }
+        match self.astar.repair(ap, &self.plan, self.step, &self.state, &self.goal) {
+            Some((plan, repair)) => {
+                self.plan = plan;
+                self.step = 0;
+                self.replans += 1;
+                self.last_repair = Some(repair);
+                ExecStatus::Replanned
+            },
+            None => ExecStatus::Failed
+        }
+    }
+
+    fn next_action(&self, ap: &ActionPlanner) -> Option<ActionId> {
+        self.current().and_then(|name| ap.lookup_action(name))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{PlanExecutor, ActionOutcome, ExecStatus};
+    use ::repair::Repair;
+    use ::goap::{ActionPlanner, WorldState};
+    use ::astar::AStar;
+    use ::test_domains::{ambush, ambush_start, enemy_dead};
+
+    fn planner() -> ActionPlanner {
+        let mut builder = ActionPlanner::builder();
+        ambush(&mut builder);
+        builder.build()
+    }
+
+    #[test]
+    fn follows_plan_to_goal() {
+        let ap = planner();
+        let goal = enemy_dead(&ap);
+        let plan = AStar::new().plan(&ap, &ambush_start(&ap), &goal).unwrap();
+        let steps: Vec<_> = plan.iter().skip(1).cloned().collect();
+        let mut exec = PlanExecutor::new(plan, goal);
+
+        for (i, (name, ws)) in steps.iter().enumerate() {
+            assert_eq!(Some(name), exec.current());
+            let status = exec.report(&ap, ActionOutcome::Succeeded, ws);
+            let expected = if i + 1 == steps.len() { ExecStatus::Done } else { ExecStatus::Continue };
+            assert_eq!(expected, status);
+        }
+        assert_eq!(0, exec.replans());
+    }
+
+    #[test]
+    fn replans_when_world_diverges() {
+        let ap = planner();
+        let goal = enemy_dead(&ap);
+        let plan = AStar::new().plan(&ap, &ambush_start(&ap), &goal).unwrap();
+        let mut exec = PlanExecutor::new(plan, goal);
+
+        // The action went through, but something undid its effect, so it goes back in
+        // before the step that needs it.
+        let undone = WorldState::builder(&ap).lacks("enemyvisible").lacks("weaponloaded").build().unwrap();
+        assert_eq!(ExecStatus::Replanned, exec.report(&ap, ActionOutcome::Succeeded, &undone));
+        assert_eq!(1, exec.replans());
+        assert_eq!(0, exec.step());
+        assert_eq!(Some(Repair::Patched { at: 2, inserted: 1 }), exec.last_repair());
+        assert_eq!(5, exec.plan().iter().count());
+
+        // A failed action replans from where the world is, even if nothing observed changed.
+        assert_eq!(ExecStatus::Replanned, exec.report(&ap, ActionOutcome::Failed, &WorldState::new()));
+        assert_eq!(2, exec.replans());
+        assert_eq!(Some(Repair::Skipped { skipped: 0 }), exec.last_repair());
+    }
+
+    #[test]
+    fn fails_when_goal_becomes_unreachable() {
+        let ap = planner();
+        let goal = enemy_dead(&ap);
+        let plan = AStar::new().plan(&ap, &ambush_start(&ap), &goal).unwrap();
+        let mut exec = PlanExecutor::new(plan, goal);
+
+        let disarmed = WorldState::builder(&ap).lacks("armedwithgun").build().unwrap();
+        assert_eq!(ExecStatus::Failed, exec.report(&ap, ActionOutcome::Failed, &disarmed));
+        assert_eq!(Some(false), exec.state().get(ap.lookup_atom("armedwithgun").unwrap()));
+    }
+}
@@ -1,14 +1,52 @@
-use itertools::Itertools;
 use itertools::multizip;
 
 use bitset::BitSetU64;
 use std::ops::IndexMut;
 use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 pub const MAX_ATOMS: usize = 64;
-pub const MAX_ACTIONS: usize = 64;
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(0);
+
+fn next_version() -> u64 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Handle to an atom registered with an `ActionPlanner`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub struct AtomId(pub(crate) usize);
+
+impl AtomId {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+
+    /// Used by `domain!`, which knows the registration order at compile time.
+    #[doc(hidden)]
+    pub const fn from_index(index: usize) -> Self {
+        AtomId(index)
+    }
+}
+
+/// Handle to an action registered with an `ActionPlanner`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub struct ActionId(pub(crate) usize);
+
+impl ActionId {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+
+    /// Used by `domain!`, which knows the registration order at compile time.
+    #[doc(hidden)]
+    pub const fn from_index(index: usize) -> Self {
+        ActionId(index)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub struct WorldState
 {
     pub values: BitSetU64,
@@ -28,21 +66,95 @@ impl WorldState {
     }
 
 
-    pub fn set(&mut self, ap: &mut ActionPlanner, atom_name: &'static str, value: bool) -> bool {
-        match ap.idx_for_atom_name(atom_name) {
-            Some(idx) => self.set_idx(idx, value),
+    /// Sets `atom_name` to `value`, returning `false` if `ap` has no such atom.
+    pub fn set(&mut self, ap: &ActionPlanner, atom_name: &str, value: bool) -> bool {
+        match ap.lookup_atom(atom_name) {
+            Some(atom) => self.set_idx(atom.0, value),
             _ => false
         }
     }
 
+    pub fn set_id(&mut self, atom: AtomId, value: bool) {
+        self.set_idx(atom.0, value);
+    }
+
     fn set_idx(&mut self, idx: usize, value: bool) -> bool {
         self.values.set(idx, value);
         self.dontcare.disable(idx);
         true
     }
 
+    /// The value of `atom`, or `None` if it is don't-care.
+    pub fn get(&self, atom: AtomId) -> Option<bool> {
+        if self.dontcare.get(atom.0) {
+            None
+        } else {
+            Some(self.values.get(atom.0))
+        }
+    }
+
+    /// Makes `atom` don't-care again.
+    pub fn unset(&mut self, atom: AtomId) {
+        self.values.disable(atom.0);
+        self.dontcare.enable(atom.0);
+    }
+
+    /// The atoms this state specifies.
+    pub(crate) fn cares(&self) -> BitSetU64 {
+        self.dontcare ^ BitSetU64::full()
+    }
+
+    fn atoms_in(bits: BitSetU64) -> impl Iterator<Item = AtomId> {
+        (0..MAX_ATOMS).filter(move |&i| bits.get(i)).map(AtomId)
+    }
+
+    /// Whether this state meets `goal`, by the same test the planner uses: every atom the goal
+    /// specifies has the goal's value here, with atoms this state leaves don't-care counting as
+    /// false.
+    pub fn satisfies(&self, goal: &WorldState) -> bool {
+        (self.values & goal.cares()) == (goal.values & goal.cares())
+    }
+
+    /// This state with every atom `other` specifies set to `other`'s value.
+    pub fn overlay(&self, other: &WorldState) -> WorldState {
+        WorldState {
+            values: (self.values & other.dontcare) | (other.values & other.cares()),
+            dontcare: self.dontcare & other.dontcare
+        }
+    }
+
+    /// Atoms both states specify with different values.
+    pub fn conflicts(&self, other: &WorldState) -> impl Iterator<Item = AtomId> {
+        let both = self.cares() & other.cares();
+        Self::atoms_in((self.values ^ other.values) & both)
+    }
+
+    /// The atoms of both states combined, or `None` if they conflict.
+    pub fn merge(&self, other: &WorldState) -> Option<WorldState> {
+        match self.conflicts(other).next() {
+            Some(_) => None,
+            None => Some(self.overlay(other))
+        }
+    }
+
+    /// Atoms whose `get` differs between the two states, including atoms only one of them
+    /// specifies.
+    pub fn diff(&self, other: &WorldState) -> impl Iterator<Item = AtomId> {
+        let specified = self.dontcare ^ other.dontcare;
+        let both = self.cares() & other.cares();
+        Self::atoms_in(specified | ((self.values ^ other.values) & both))
+    }
+
+    /// The atoms this state specifies, by name, with their values.
+    pub fn iter<'a>(&self, ap: &'a ActionPlanner) -> impl Iterator<Item = (&'a str, bool)> {
+        let ws = *self;
+        ap.atom_ids()
+            .filter(move |&atom| !ws.dontcare.get(atom.0))
+            .map(move |atom| (&**ap.atom_name(atom), ws.values.get(atom.0)))
+    }
+
     pub fn debug_fmt<'a, 'b>(&'a self, ap: &'b ActionPlanner) -> WorldStateFmt<'a, 'b>  {
-        WorldStateFmt{ws: self, ap: ap}
+        WorldStateFmt{ws: self, ap}
     }
 }
 
@@ -61,130 +173,258 @@ impl<'a, 'b> fmt::Debug for WorldStateFmt<'a, 'b> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for i in 0..MAX_ATOMS {
             if !self.ws.dontcare.get(i) {
-                let string = self.ap.atm_names.get(i).unwrap();
+                let string = &self.ap.atm_names[i];
                 if self.ws.values.get(i) {
-                    write!(f, "+{}\n", string).unwrap();
+                    writeln!(f, "+{}", string).unwrap();
                 } else {
-                    write!(f, "-{}\n", string).unwrap();
+                    writeln!(f, "-{}", string).unwrap();
                 }
             }
         }
-        write!(f, "\n")
+        writeln!(f)
     }
 }
 
+/// A compiled domain together with the start and goal states of one planning problem.
+#[derive(Debug)]
+pub struct Problem {
+    pub planner: ActionPlanner,
+    pub start: WorldState,
+    pub goal: WorldState
+}
+
+/// Parallel slices of action names, preconditions, costs and effects.
+pub type Actions<'a> = (&'a [Arc<str>], &'a [WorldState], &'a [i32], &'a [WorldState]);
+
+/// A compiled planning domain.
+///
+/// An `ActionPlanner` is produced by an `ActionPlannerBuilder` and never changes afterwards,
+/// so lookups only read and a single planner can be shared between threads.
 pub struct ActionPlanner {
-    atm_names: [&'static str; MAX_ATOMS],
-    num_atoms: usize,
-
-    act_names: [&'static str; MAX_ACTIONS],
-    act_pre: [WorldState; MAX_ACTIONS],
-    act_post: [WorldState; MAX_ACTIONS],
-    act_costs: [i32; MAX_ACTIONS],
-    num_actions: usize,
+    atm_names: Vec<Arc<str>>,
+
+    act_names: Vec<Arc<str>>,
+    act_pre: Vec<WorldState>,
+    act_post: Vec<WorldState>,
+    act_costs: Vec<i32>,
+    version: u64
 }
 
 
 impl ActionPlanner {
-    pub fn new() -> Self {
+    fn empty() -> Self {
         ActionPlanner {
-            atm_names: [Default::default(); MAX_ATOMS],
-            num_atoms: 0,
-            act_names: [Default::default(); MAX_ACTIONS],
-            act_pre: [Default::default(); MAX_ACTIONS],
-            act_post: [Default::default(); MAX_ACTIONS],
-            act_costs: [0; MAX_ACTIONS],
-            num_actions: 0,
+            atm_names: Vec::with_capacity(MAX_ATOMS),
+            act_names: Vec::new(),
+            act_pre: Vec::new(),
+            act_post: Vec::new(),
+            act_costs: Vec::new(),
+            version: next_version()
         }
     }
 
+    pub fn builder() -> ActionPlannerBuilder {
+        ActionPlannerBuilder::new()
+    }
+
+    /// Turns the planner back into a builder so more atoms and actions can be added.
+    pub fn into_builder(self) -> ActionPlannerBuilder {
+        ActionPlannerBuilder{ap: self}
+    }
+
+    /// Identifies this planner's domain. Every planner built gets a new version, so one
+    /// rebuilt through `into_builder` never shares a version with the planner it came from.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn lookup_atom(&self, atom_name: &str) -> Option<AtomId> {
+        self.atm_names.iter()
+            .position(|s| &**s == atom_name)
+            .map(AtomId)
+    }
+
+    pub fn lookup_action(&self, act_name: &str) -> Option<ActionId> {
+        self.act_names.iter()
+            .position(|s| &**s == act_name)
+            .map(ActionId)
+    }
+
+    pub fn atom_names(&self) -> &[Arc<str>] {
+        &self.atm_names
+    }
+
+    pub fn atom_name(&self, atom: AtomId) -> &Arc<str> {
+        &self.atm_names[atom.0]
+    }
+
+    pub fn atom_ids(&self) -> impl Iterator<Item = AtomId> {
+        (0..self.atm_names.len()).map(AtomId)
+    }
+
+    pub fn action_ids(&self) -> impl Iterator<Item = ActionId> {
+        (0..self.act_names.len()).map(ActionId)
+    }
+
+    pub fn act_pre(&self) -> &[WorldState] {
+        &self.act_pre
+    }
+
+    /// The shared name of `action`; cloning it only bumps a reference count.
+    pub fn name(&self, action: ActionId) -> &Arc<str> {
+        &self.act_names[action.0]
+    }
+
+    pub fn cost(&self, action: ActionId) -> i32 {
+        self.act_costs[action.0]
+    }
+
+    pub fn pre(&self, action: ActionId) -> &WorldState {
+        &self.act_pre[action.0]
+    }
+
+    pub fn post(&self, action: ActionId) -> &WorldState {
+        &self.act_post[action.0]
+    }
+
+    /// Whether `ws` meets the preconditions of `action`.
+    pub fn is_applicable(&self, action: ActionId, ws: &WorldState) -> bool {
+        let pre = &self.act_pre[action.0];
+        (pre.values & pre.cares()) == (ws.values & pre.cares())
+    }
+
+    /// The state `action` leads to from `ws`. Preconditions are not checked; see
+    /// `is_applicable`.
+    pub fn apply(&self, action: ActionId, ws: &WorldState) -> WorldState {
+        let post = &self.act_post[action.0];
+        let unaffected = post.dontcare;
+        let affected = unaffected ^ BitSetU64::full();
+        WorldState {
+            values: (ws.values & unaffected) | (post.values & affected),
+            dontcare: ws.dontcare & unaffected
+        }
+    }
+
+    /// The actions applicable in `ws`, in registration order.
+    pub fn applicable_actions<'a>(&'a self, ws: &WorldState) -> impl Iterator<Item = ActionId> + 'a {
+        let ws = *ws;
+        self.action_ids().filter(move |&action| self.is_applicable(action, &ws))
+    }
+
+    pub fn actions(&self) -> Actions<'_> {
+        (&self.act_names,
+         &self.act_pre,
+         &self.act_costs,
+         &self.act_post
+        )
+    }
+
+}
+
+impl Default for ActionPlanner {
+    fn default() -> Self {
+        ActionPlanner::empty()
+    }
+}
+
+/// Registers the atoms and actions of a domain and compiles them into an `ActionPlanner`.
+pub struct ActionPlannerBuilder {
+    ap: ActionPlanner
+}
+
+impl ActionPlannerBuilder {
+    pub fn new() -> Self {
+        ActionPlannerBuilder{ap: ActionPlanner::empty()}
+    }
+
     pub fn clear(&mut self) {
-        *self = ActionPlanner::new();
-    }
-
-    pub fn idx_for_atom_name(&mut self, atom_name: &'static str) -> Option<usize> {
-        let find_index = self.atm_names[0..self.num_atoms].iter()
-            .position(|s| *s == atom_name);
-        match find_index {
-            Some(_) => find_index,
-            None if self.num_atoms < MAX_ATOMS => {
-                let idx = self.num_atoms;
-                *self.atm_names.index_mut(self.num_atoms) = atom_name;
-                self.num_atoms += 1;
-                Some(idx)
+        *self = ActionPlannerBuilder::new();
+    }
+
+    pub fn build(mut self) -> ActionPlanner {
+        self.ap.version = next_version();
+        self.ap
+    }
+
+    /// Returns the handle for `atom_name`, registering the atom if it is new.
+    pub fn register_atom(&mut self, atom_name: &str) -> Option<AtomId> {
+        let ap = &mut self.ap;
+        match ap.lookup_atom(atom_name) {
+            Some(atom) => Some(atom),
+            None if ap.atm_names.len() < MAX_ATOMS => {
+                ap.atm_names.push(Arc::from(atom_name));
+                Some(AtomId(ap.atm_names.len() - 1))
             },
             None => None
         }
     }
 
-    pub fn idx_for_action_name(&mut self, act_name: &'static str) -> Option<usize> {
-        let find_index = self.act_names[0..self.num_actions].iter()
-            .position(|s| *s == act_name);
-        match find_index {
-            Some(_) => find_index,
-            None if self.num_actions < MAX_ACTIONS => {
-                let idx = self.num_actions;
-                *self.act_names.index_mut(idx) = act_name;
-                *self.act_costs.index_mut(idx) = 1;
-                self.num_actions += 1;
-                Some(idx)
-            },
-            None => None
+    /// Returns the handle for `act_name`, registering the action if it is new.
+    pub fn register_action(&mut self, act_name: &str) -> Option<ActionId> {
+        let ap = &mut self.ap;
+        match ap.lookup_action(act_name) {
+            Some(action) => Some(action),
+            None => {
+                ap.act_names.push(Arc::from(act_name));
+                ap.act_pre.push(WorldState::new());
+                ap.act_post.push(WorldState::new());
+                ap.act_costs.push(1);
+                Some(ActionId(ap.act_names.len() - 1))
+            }
         }
     }
 
-    pub fn set_pre(&mut self, action_name: &'static str, atom_name: &'static str, value: bool) -> bool {
-        match (self.idx_for_action_name(action_name), self.idx_for_atom_name(atom_name)) {
-            (Some(actidx), Some(atmidx)) => self.act_pre.index_mut(actidx).set_idx(atmidx, value),
+    pub fn lookup_atom(&self, atom_name: &str) -> Option<AtomId> {
+        self.ap.lookup_atom(atom_name)
+    }
+
+    pub fn lookup_action(&self, act_name: &str) -> Option<ActionId> {
+        self.ap.lookup_action(act_name)
+    }
+
+    pub fn atom_names(&self) -> &[Arc<str>] {
+        self.ap.atom_names()
+    }
+
+    pub fn set_pre(&mut self, action_name: &str, atom_name: &str, value: bool) -> bool {
+        match (self.register_action(action_name), self.register_atom(atom_name)) {
+            (Some(action), Some(atom)) => self.ap.act_pre.index_mut(action.0).set_idx(atom.0, value),
             _ => false
         }
     }
 
-    pub fn set_post(&mut self, action_name: &'static str, atom_name: &'static str, value: bool) -> bool {
-        match (self.idx_for_action_name(action_name), self.idx_for_atom_name(atom_name)) {
-            (Some(actidx), Some(atmidx)) => self.act_post.index_mut(actidx).set_idx(atmidx, value),
+    pub fn set_post(&mut self, action_name: &str, atom_name: &str, value: bool) -> bool {
+        match (self.register_action(action_name), self.register_atom(atom_name)) {
+            (Some(action), Some(atom)) => self.ap.act_post.index_mut(action.0).set_idx(atom.0, value),
             _ => false
         }
     }
 
-    pub fn set_cost(&mut self, action_name: &'static str, cost: i32) -> bool {
-        if let Some(idx) = self.idx_for_action_name(action_name) {
-            *self.act_costs.index_mut(idx) = cost;
+    pub fn set_cost(&mut self, action_name: &str, cost: i32) -> bool {
+        if let Some(action) = self.register_action(action_name) {
+            self.set_cost_id(action, cost);
             true
         } else {
             false
         }
     }
 
-    pub fn act_pre(&self) -> &[WorldState] {
-        &self.act_pre[..self.num_actions]
-    }
-
-    pub fn name(&self, i: usize) -> &'static str {
-        self.act_names[..self.num_actions].get(i).unwrap()
+    pub fn set_pre_id(&mut self, action: ActionId, atom: AtomId, value: bool) {
+        self.ap.act_pre.index_mut(action.0).set_idx(atom.0, value);
     }
 
-    pub fn cost(&self, i: usize) -> i32 {
-        *self.act_costs[..self.num_actions].get(i).unwrap()
+    pub fn set_post_id(&mut self, action: ActionId, atom: AtomId, value: bool) {
+        self.ap.act_post.index_mut(action.0).set_idx(atom.0, value);
     }
 
-    pub fn post(&self, i: usize) -> &WorldState {
-        self.act_post[..self.num_actions].get(i).unwrap()
+    pub fn set_cost_id(&mut self, action: ActionId, cost: i32) {
+        *self.ap.act_costs.index_mut(action.0) = cost;
     }
-
-    pub fn actions(&self) -> (&[&'static str], &[WorldState], &[i32], &[WorldState]) {
-        (&self.act_names[..self.num_actions],
-         &self.act_pre[..self.num_actions],
-         &self.act_costs[..self.num_actions],
-         &self.act_post[..self.num_actions]
-        )
-    }
-
 }
 
-impl Default for ActionPlanner {
+impl Default for ActionPlannerBuilder {
     fn default() -> Self {
-        ActionPlanner::new()
+        ActionPlannerBuilder::new()
     }
 }
 
@@ -192,20 +432,20 @@ impl fmt::Debug for ActionPlanner {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 
         for (name, pre, cost, post) in multizip(self.actions()) {
-            write!(f, "{} - {}\n", name, cost).unwrap();
+            writeln!(f, "{} - {}", name, cost).unwrap();
             for i in 0..MAX_ATOMS {
                 if !pre.dontcare.get(i) {
-                    write!(f, "  {}=={}\n", self.atm_names.get(i).unwrap(), pre.values.get(i)).unwrap();
+                    writeln!(f, "  {}=={}", self.atm_names[i], pre.values.get(i)).unwrap();
                 }
             }
             for i in 0..MAX_ATOMS {
                 if !post.dontcare.get(i) {
-                    write!(f, "  {}:={}\n", self.atm_names.get(i).unwrap(), post.values.get(i)).unwrap();
+                    writeln!(f, "  {}:={}", self.atm_names[i], post.values.get(i)).unwrap();
 
                 }
             }
         }
-        write!(f, "\n")
+        writeln!(f)
     }
 }
 
@@ -213,83 +453,245 @@ impl fmt::Debug for ActionPlanner {
 #[cfg(test)]
 mod tests {
 
-    use super::ActionPlanner;
+    use super::{ActionPlanner, ActionPlannerBuilder, WorldState};
 
     #[test]
     fn it_works() {
-        let mut planner = ActionPlanner::new();
-        assert_eq!(Some(0), planner.idx_for_atom_name("atom_0"));
-        assert_eq!(Some(1), planner.idx_for_atom_name("atom_1"));
-        assert_eq!(Some(2), planner.idx_for_atom_name("atom_2"));
-        assert_eq!(Some(3), planner.idx_for_atom_name("atom_3"));
-        assert_eq!(Some(4), planner.idx_for_atom_name("atom_4"));
-        assert_eq!(Some(5), planner.idx_for_atom_name("atom_5"));
-        assert_eq!(Some(6), planner.idx_for_atom_name("atom_6"));
-        assert_eq!(Some(7), planner.idx_for_atom_name("atom_7"));
-        assert_eq!(Some(8), planner.idx_for_atom_name("atom_8"));
-        assert_eq!(Some(9), planner.idx_for_atom_name("atom_9"));
-        assert_eq!(Some(10), planner.idx_for_atom_name("atom_10"));
-        assert_eq!(Some(11), planner.idx_for_atom_name("atom_11"));
-        assert_eq!(Some(12), planner.idx_for_atom_name("atom_12"));
-        assert_eq!(Some(13), planner.idx_for_atom_name("atom_13"));
-        assert_eq!(Some(14), planner.idx_for_atom_name("atom_14"));
-        assert_eq!(Some(15), planner.idx_for_atom_name("atom_15"));
-        assert_eq!(Some(16), planner.idx_for_atom_name("atom_16"));
-        assert_eq!(Some(17), planner.idx_for_atom_name("atom_17"));
-        assert_eq!(Some(18), planner.idx_for_atom_name("atom_18"));
-        assert_eq!(Some(19), planner.idx_for_atom_name("atom_19"));
-        assert_eq!(Some(20), planner.idx_for_atom_name("atom_20"));
-        assert_eq!(Some(21), planner.idx_for_atom_name("atom_21"));
-        assert_eq!(Some(22), planner.idx_for_atom_name("atom_22"));
-        assert_eq!(Some(23), planner.idx_for_atom_name("atom_23"));
-        assert_eq!(Some(24), planner.idx_for_atom_name("atom_24"));
-        assert_eq!(Some(25), planner.idx_for_atom_name("atom_25"));
-        assert_eq!(Some(26), planner.idx_for_atom_name("atom_26"));
-        assert_eq!(Some(27), planner.idx_for_atom_name("atom_27"));
-        assert_eq!(Some(28), planner.idx_for_atom_name("atom_28"));
-        assert_eq!(Some(29), planner.idx_for_atom_name("atom_29"));
-        assert_eq!(Some(30), planner.idx_for_atom_name("atom_30"));
-        assert_eq!(Some(31), planner.idx_for_atom_name("atom_31"));
-        assert_eq!(Some(32), planner.idx_for_atom_name("atom_32"));
-        assert_eq!(Some(33), planner.idx_for_atom_name("atom_33"));
-        assert_eq!(Some(34), planner.idx_for_atom_name("atom_34"));
-        assert_eq!(Some(35), planner.idx_for_atom_name("atom_35"));
-        assert_eq!(Some(36), planner.idx_for_atom_name("atom_36"));
-        assert_eq!(Some(37), planner.idx_for_atom_name("atom_37"));
-        assert_eq!(Some(38), planner.idx_for_atom_name("atom_38"));
-        assert_eq!(Some(39), planner.idx_for_atom_name("atom_39"));
-        assert_eq!(Some(40), planner.idx_for_atom_name("atom_40"));
-        assert_eq!(Some(41), planner.idx_for_atom_name("atom_41"));
-        assert_eq!(Some(42), planner.idx_for_atom_name("atom_42"));
-        assert_eq!(Some(43), planner.idx_for_atom_name("atom_43"));
-        assert_eq!(Some(44), planner.idx_for_atom_name("atom_44"));
-        assert_eq!(Some(45), planner.idx_for_atom_name("atom_45"));
-        assert_eq!(Some(46), planner.idx_for_atom_name("atom_46"));
-        assert_eq!(Some(47), planner.idx_for_atom_name("atom_47"));
-        assert_eq!(Some(48), planner.idx_for_atom_name("atom_48"));
-        assert_eq!(Some(49), planner.idx_for_atom_name("atom_49"));
-        assert_eq!(Some(50), planner.idx_for_atom_name("atom_50"));
-        assert_eq!(Some(51), planner.idx_for_atom_name("atom_51"));
-        assert_eq!(Some(52), planner.idx_for_atom_name("atom_52"));
-        assert_eq!(Some(53), planner.idx_for_atom_name("atom_53"));
-        assert_eq!(Some(54), planner.idx_for_atom_name("atom_54"));
-        assert_eq!(Some(55), planner.idx_for_atom_name("atom_55"));
-        assert_eq!(Some(56), planner.idx_for_atom_name("atom_56"));
-        assert_eq!(Some(57), planner.idx_for_atom_name("atom_57"));
-        assert_eq!(Some(58), planner.idx_for_atom_name("atom_58"));
-        assert_eq!(Some(59), planner.idx_for_atom_name("atom_59"));
-        assert_eq!(Some(60), planner.idx_for_atom_name("atom_60"));
-        assert_eq!(Some(61), planner.idx_for_atom_name("atom_61"));
-        assert_eq!(Some(62), planner.idx_for_atom_name("atom_62"));
-        assert_eq!(Some(63), planner.idx_for_atom_name("atom_63"));
-        assert_eq!(None, planner.idx_for_atom_name("atom_64"));
-        assert_eq!(None, planner.idx_for_atom_name("atom_65"));
-        assert_eq!(None, planner.idx_for_atom_name("atom_66"));
-        assert_eq!(None, planner.idx_for_atom_name("atom_67"));
-        assert_eq!(None, planner.idx_for_atom_name("atom_68"));
-        assert_eq!(None, planner.idx_for_atom_name("atom_69"));
-
-
-        assert_eq!(Some(0), planner.idx_for_atom_name("atom_0"));
-    }
-}
\ No newline at end of file
+        let mut planner = ActionPlannerBuilder::new();
+        assert_eq!(Some(0), planner.register_atom("atom_0").map(|a| a.index()));
+        assert_eq!(Some(1), planner.register_atom("atom_1").map(|a| a.index()));
+        assert_eq!(Some(2), planner.register_atom("atom_2").map(|a| a.index()));
+        assert_eq!(Some(3), planner.register_atom("atom_3").map(|a| a.index()));
+        assert_eq!(Some(4), planner.register_atom("atom_4").map(|a| a.index()));
+        assert_eq!(Some(5), planner.register_atom("atom_5").map(|a| a.index()));
+        assert_eq!(Some(6), planner.register_atom("atom_6").map(|a| a.index()));
+        assert_eq!(Some(7), planner.register_atom("atom_7").map(|a| a.index()));
+        assert_eq!(Some(8), planner.register_atom("atom_8").map(|a| a.index()));
+        assert_eq!(Some(9), planner.register_atom("atom_9").map(|a| a.index()));
+        assert_eq!(Some(10), planner.register_atom("atom_10").map(|a| a.index()));
+        assert_eq!(Some(11), planner.register_atom("atom_11").map(|a| a.index()));
+        assert_eq!(Some(12), planner.register_atom("atom_12").map(|a| a.index()));
+        assert_eq!(Some(13), planner.register_atom("atom_13").map(|a| a.index()));
+        assert_eq!(Some(14), planner.register_atom("atom_14").map(|a| a.index()));
+        assert_eq!(Some(15), planner.register_atom("atom_15").map(|a| a.index()));
+        assert_eq!(Some(16), planner.register_atom("atom_16").map(|a| a.index()));
+        assert_eq!(Some(17), planner.register_atom("atom_17").map(|a| a.index()));
+        assert_eq!(Some(18), planner.register_atom("atom_18").map(|a| a.index()));
+        assert_eq!(Some(19), planner.register_atom("atom_19").map(|a| a.index()));
+        assert_eq!(Some(20), planner.register_atom("atom_20").map(|a| a.index()));
+        assert_eq!(Some(21), planner.register_atom("atom_21").map(|a| a.index()));
+        assert_eq!(Some(22), planner.register_atom("atom_22").map(|a| a.index()));
+        assert_eq!(Some(23), planner.register_atom("atom_23").map(|a| a.index()));
+        assert_eq!(Some(24), planner.register_atom("atom_24").map(|a| a.index()));
+        assert_eq!(Some(25), planner.register_atom("atom_25").map(|a| a.index()));
+        assert_eq!(Some(26), planner.register_atom("atom_26").map(|a| a.index()));
+        assert_eq!(Some(27), planner.register_atom("atom_27").map(|a| a.index()));
+        assert_eq!(Some(28), planner.register_atom("atom_28").map(|a| a.index()));
+        assert_eq!(Some(29), planner.register_atom("atom_29").map(|a| a.index()));
+        assert_eq!(Some(30), planner.register_atom("atom_30").map(|a| a.index()));
+        assert_eq!(Some(31), planner.register_atom("atom_31").map(|a| a.index()));
+        assert_eq!(Some(32), planner.register_atom("atom_32").map(|a| a.index()));
+        assert_eq!(Some(33), planner.register_atom("atom_33").map(|a| a.index()));
+        assert_eq!(Some(34), planner.register_atom("atom_34").map(|a| a.index()));
+        assert_eq!(Some(35), planner.register_atom("atom_35").map(|a| a.index()));
+        assert_eq!(Some(36), planner.register_atom("atom_36").map(|a| a.index()));
+        assert_eq!(Some(37), planner.register_atom("atom_37").map(|a| a.index()));
+        assert_eq!(Some(38), planner.register_atom("atom_38").map(|a| a.index()));
+        assert_eq!(Some(39), planner.register_atom("atom_39").map(|a| a.index()));
+        assert_eq!(Some(40), planner.register_atom("atom_40").map(|a| a.index()));
+        assert_eq!(Some(41), planner.register_atom("atom_41").map(|a| a.index()));
+        assert_eq!(Some(42), planner.register_atom("atom_42").map(|a| a.index()));
+        assert_eq!(Some(43), planner.register_atom("atom_43").map(|a| a.index()));
+        assert_eq!(Some(44), planner.register_atom("atom_44").map(|a| a.index()));
+        assert_eq!(Some(45), planner.register_atom("atom_45").map(|a| a.index()));
+        assert_eq!(Some(46), planner.register_atom("atom_46").map(|a| a.index()));
+        assert_eq!(Some(47), planner.register_atom("atom_47").map(|a| a.index()));
+        assert_eq!(Some(48), planner.register_atom("atom_48").map(|a| a.index()));
+        assert_eq!(Some(49), planner.register_atom("atom_49").map(|a| a.index()));
+        assert_eq!(Some(50), planner.register_atom("atom_50").map(|a| a.index()));
+        assert_eq!(Some(51), planner.register_atom("atom_51").map(|a| a.index()));
+        assert_eq!(Some(52), planner.register_atom("atom_52").map(|a| a.index()));
+        assert_eq!(Some(53), planner.register_atom("atom_53").map(|a| a.index()));
+        assert_eq!(Some(54), planner.register_atom("atom_54").map(|a| a.index()));
+        assert_eq!(Some(55), planner.register_atom("atom_55").map(|a| a.index()));
+        assert_eq!(Some(56), planner.register_atom("atom_56").map(|a| a.index()));
+        assert_eq!(Some(57), planner.register_atom("atom_57").map(|a| a.index()));
+        assert_eq!(Some(58), planner.register_atom("atom_58").map(|a| a.index()));
+        assert_eq!(Some(59), planner.register_atom("atom_59").map(|a| a.index()));
+        assert_eq!(Some(60), planner.register_atom("atom_60").map(|a| a.index()));
+        assert_eq!(Some(61), planner.register_atom("atom_61").map(|a| a.index()));
+        assert_eq!(Some(62), planner.register_atom("atom_62").map(|a| a.index()));
+        assert_eq!(Some(63), planner.register_atom("atom_63").map(|a| a.index()));
+        assert_eq!(None, planner.register_atom("atom_64"));
+        assert_eq!(None, planner.register_atom("atom_65"));
+        assert_eq!(None, planner.register_atom("atom_66"));
+        assert_eq!(None, planner.register_atom("atom_67"));
+        assert_eq!(None, planner.register_atom("atom_68"));
+        assert_eq!(None, planner.register_atom("atom_69"));
+
+
+        assert_eq!(Some(0), planner.register_atom("atom_0").map(|a| a.index()));
+    }
+
+    #[test]
+    fn runtime_names() {
+        let mut builder = ActionPlanner::builder();
+        for i in 0..3 {
+            let action = format!("action_{}", i);
+            let atom = format!("atom_{}", i);
+            assert!(builder.set_pre(&action, &atom, true));
+        }
+        let planner = builder.build();
+        assert_eq!(Some(1), planner.lookup_atom(&String::from("atom_1")).map(|a| a.index()));
+        let action = planner.lookup_action("action_2").unwrap();
+        assert_eq!("action_2", &**planner.name(action));
+        assert_eq!(3, planner.atom_names().len());
+    }
+
+    #[test]
+    fn handles() {
+        let mut builder = ActionPlanner::builder();
+        let aim = builder.register_action("aim").unwrap();
+        let visible = builder.register_atom("enemyvisible").unwrap();
+        let linedup = builder.register_atom("enemylinedup").unwrap();
+        assert_eq!(Some(aim), builder.register_action("aim"));
+        assert_eq!(Some(visible), builder.register_atom("enemyvisible"));
+
+        builder.set_pre_id(aim, visible, true);
+        builder.set_post_id(aim, linedup, true);
+        builder.set_cost_id(aim, 3);
+        let planner = builder.build();
+        assert_eq!(3, planner.cost(aim));
+        assert_eq!("enemylinedup", &**planner.atom_name(linedup));
+
+        let mut ws = WorldState::new();
+        ws.set_id(visible, true);
+        assert_eq!(*planner.pre(aim), ws);
+        assert_eq!(vec![visible, linedup], planner.atom_ids().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn applies_actions() {
+        let mut builder = ActionPlanner::builder();
+        builder.set_pre("load", "armedwithgun", true);
+        builder.set_post("load", "weaponloaded", true);
+        builder.set_pre("shoot", "weaponloaded", true);
+        builder.set_post("shoot", "enemyalive", false);
+        builder.set_post("drop", "armedwithgun", false);
+        let planner = builder.build();
+        let load = planner.lookup_action("load").unwrap();
+        let shoot = planner.lookup_action("shoot").unwrap();
+        let drop = planner.lookup_action("drop").unwrap();
+
+        let mut ws = WorldState::new();
+        ws.set(&planner, "armedwithgun", true);
+        ws.set(&planner, "weaponloaded", false);
+        assert!(planner.is_applicable(load, &ws));
+        assert!(!planner.is_applicable(shoot, &ws));
+        assert_eq!(vec![load, drop], planner.applicable_actions(&ws).collect::<Vec<_>>());
+
+        let loaded = planner.apply(load, &ws);
+        let mut expected = ws;
+        expected.set(&planner, "weaponloaded", true);
+        assert_eq!(expected, loaded);
+        assert_eq!(vec![load, shoot, drop], planner.applicable_actions(&loaded).collect::<Vec<_>>());
+
+        let mut dead = loaded;
+        dead.set(&planner, "enemyalive", false);
+        assert_eq!(dead, planner.apply(shoot, &loaded));
+    }
+
+    fn combat() -> ActionPlanner {
+        let mut builder = ActionPlanner::builder();
+        for atom in &["armedwithgun", "weaponloaded", "enemyalive", "alive"] {
+            builder.register_atom(atom);
+        }
+        builder.build()
+    }
+
+    fn state(ap: &ActionPlanner, values: &[(&str, bool)]) -> WorldState {
+        let mut ws = WorldState::new();
+        for &(name, value) in values {
+            assert!(ws.set(ap, name, value));
+        }
+        ws
+    }
+
+    #[test]
+    fn get_and_unset() {
+        let ap = combat();
+        let gun = ap.lookup_atom("armedwithgun").unwrap();
+        let mut ws = state(&ap, &[("armedwithgun", true), ("alive", false)]);
+        assert_eq!(Some(true), ws.get(gun));
+        assert_eq!(Some(false), ws.get(ap.lookup_atom("alive").unwrap()));
+        assert_eq!(None, ws.get(ap.lookup_atom("enemyalive").unwrap()));
+        ws.unset(gun);
+        assert_eq!(None, ws.get(gun));
+        assert_eq!(state(&ap, &[("alive", false)]), ws);
+    }
+
+    #[test]
+    fn satisfies_goals() {
+        let ap = combat();
+        let ws = state(&ap, &[("armedwithgun", true), ("enemyalive", false)]);
+        assert!(ws.satisfies(&WorldState::new()));
+        assert!(ws.satisfies(&state(&ap, &[("enemyalive", false)])));
+        assert!(!ws.satisfies(&state(&ap, &[("enemyalive", false), ("armedwithgun", false)])));
+        // As in the planner, unspecified atoms count as false.
+        assert!(ws.satisfies(&state(&ap, &[("alive", false)])));
+        assert!(!ws.satisfies(&state(&ap, &[("alive", true)])));
+    }
+
+    #[test]
+    fn overlay_merge_and_conflicts() {
+        let ap = combat();
+        let base = state(&ap, &[("armedwithgun", true), ("weaponloaded", false)]);
+        let update = state(&ap, &[("weaponloaded", true), ("alive", true)]);
+        assert_eq!(state(&ap, &[("armedwithgun", true), ("weaponloaded", true), ("alive", true)]),
+                   base.overlay(&update));
+        assert_eq!(vec![ap.lookup_atom("weaponloaded").unwrap()], base.conflicts(&update).collect::<Vec<_>>());
+        assert_eq!(None, base.merge(&update));
+
+        let extra = state(&ap, &[("armedwithgun", true), ("enemyalive", true)]);
+        assert_eq!(0, base.conflicts(&extra).count());
+        assert_eq!(Some(state(&ap, &[("armedwithgun", true), ("weaponloaded", false), ("enemyalive", true)])),
+                   base.merge(&extra));
+    }
+
+    #[test]
+    fn diff_lists_changed_atoms() {
+        let ap = combat();
+        let before = state(&ap, &[("armedwithgun", true), ("weaponloaded", false), ("alive", true)]);
+        let after = state(&ap, &[("armedwithgun", true), ("weaponloaded", true), ("enemyalive", false)]);
+        let names: Vec<&str> = before.diff(&after).map(|atom| &**ap.atom_name(atom)).collect();
+        assert_eq!(vec!["weaponloaded", "enemyalive", "alive"], names);
+        assert_eq!(0, before.diff(&before).count());
+    }
+
+    #[test]
+    fn iterates_specified_atoms() {
+        let ap = combat();
+        let ws = state(&ap, &[("alive", true), ("weaponloaded", false)]);
+        assert_eq!(vec![("weaponloaded", false), ("alive", true)], ws.iter(&ap).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn lookup_does_not_register() {
+        let mut builder = ActionPlanner::builder();
+        builder.set_post("load", "weaponloaded", true);
+        let planner = builder.build();
+
+        let mut ws = WorldState::new();
+        assert!(ws.set(&planner, "weaponloaded", true));
+        assert!(!ws.set(&planner, "weaponlaoded", true));
+        assert_eq!(None, planner.lookup_atom("weaponlaoded"));
+        assert_eq!(1, planner.atom_names().len());
+    }
+
+    #[test]
+    fn planner_is_shareable() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ActionPlanner>();
+        assert_send_sync::<WorldState>();
+    }
+}
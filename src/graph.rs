@@ -0,0 +1,162 @@
+use ::goap::{WorldState, ActionPlanner, ActionId};
+use ::astar::StateTransIter;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+/// A step between two states of a `StateGraph`, given as indices into `StateGraph::states`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Transition {
+    pub from: usize,
+    pub to: usize,
+    pub action: ActionId,
+    pub cost: i32
+}
+
+/// The states reachable from a start state and the transitions between them, built by
+/// `reachable_states`. The start state is `states[0]`.
+#[derive(Debug, Clone)]
+pub struct StateGraph {
+    pub states: Vec<WorldState>,
+    pub transitions: Vec<Transition>,
+    /// Whether every reachable state was visited. When the node limit is hit, transitions to
+    /// states beyond the limit are left out.
+    pub complete: bool
+}
+
+/// Identifies a state by which atoms it cares about and their values, ignoring whatever is
+/// left in the value bits of don't-care atoms.
+pub(crate) fn state_key(ws: &WorldState) -> (u64, u64) {
+    ((ws.values & ws.cares()).bits(), ws.dontcare.bits())
+}
+
+/// Enumerates the states reachable from `start` breadth first, visiting at most
+/// `max_states` of them.
+pub fn reachable_states(ap: &ActionPlanner, start: &WorldState, max_states: usize) -> StateGraph {
+    let mut graph = StateGraph { states: Vec::new(), transitions: Vec::new(), complete: true };
+    let mut index = HashMap::new();
+    let mut queue = VecDeque::new();
+    if max_states == 0 {
+        graph.complete = false;
+        return graph;
+    }
+    graph.states.push(*start);
+    index.insert(state_key(start), 0);
+    queue.push_back(0);
+
+    while let Some(from) = queue.pop_front() {
+        let ws = graph.states[from];
+        for (action, cost, next) in StateTransIter::new(&ws, ap) {
+            let key = state_key(&next);
+            let to = match index.get(&key) {
+                Some(&to) => to,
+                None if graph.states.len() < max_states => {
+                    graph.states.push(next);
+                    index.insert(key, graph.states.len() - 1);
+                    queue.push_back(graph.states.len() - 1);
+                    graph.states.len() - 1
+                },
+                None => {
+                    graph.complete = false;
+                    continue;
+                }
+            };
+            graph.transitions.push(Transition { from, to, action, cost });
+        }
+    }
+    graph
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+/// Writes `graph` as GraphML. Nodes carry their state as `+name`/`-name` lines and whether
+/// they are the start; edges carry their action name and cost.
+pub fn write_state_graph_graphml(graph: &StateGraph, ap: &ActionPlanner) -> String {
+    let mut out = String::new();
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+    writeln!(out, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">").unwrap();
+    writeln!(out, "  <key id=\"state\" for=\"node\" attr.name=\"state\" attr.type=\"string\"/>").unwrap();
+    writeln!(out, "  <key id=\"start\" for=\"node\" attr.name=\"start\" attr.type=\"boolean\"/>").unwrap();
+    writeln!(out, "  <key id=\"action\" for=\"edge\" attr.name=\"action\" attr.type=\"string\"/>").unwrap();
+    writeln!(out, "  <key id=\"cost\" for=\"edge\" attr.name=\"cost\" attr.type=\"int\"/>").unwrap();
+    writeln!(out, "  <graph id=\"states\" edgedefault=\"directed\">").unwrap();
+    for (i, ws) in graph.states.iter().enumerate() {
+        writeln!(out, "    <node id=\"n{}\">", i).unwrap();
+        writeln!(out, "      <data key=\"state\">{}</data>", escape_xml(&::dot::state_lines(ap, ws))).unwrap();
+        writeln!(out, "      <data key=\"start\">{}</data>", i == 0).unwrap();
+        writeln!(out, "    </node>").unwrap();
+    }
+    for (i, t) in graph.transitions.iter().enumerate() {
+        writeln!(out, "    <edge id=\"e{}\" source=\"n{}\" target=\"n{}\">", i, t.from, t.to).unwrap();
+        writeln!(out, "      <data key=\"action\">{}</data>", escape_xml(ap.name(t.action))).unwrap();
+        writeln!(out, "      <data key=\"cost\">{}</data>", t.cost).unwrap();
+        writeln!(out, "    </edge>").unwrap();
+    }
+    writeln!(out, "  </graph>").unwrap();
+    writeln!(out, "</graphml>").unwrap();
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{reachable_states, write_state_graph_graphml};
+    use ::goap::{ActionPlanner, WorldState};
+    use ::dot::write_state_graph_dot;
+    use ::test_domains::gunner;
+
+    fn planner() -> ActionPlanner {
+        let mut builder = ActionPlanner::builder();
+        gunner(&mut builder);
+        builder.action("unload").requires("weaponloaded").clears("weaponloaded").build().unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn enumerates_reachable_states() {
+        let ap = planner();
+        let start = WorldState::builder(&ap)
+            .holds("armedwithgun").lacks("weaponloaded").holds("enemyalive")
+            .build().unwrap();
+        let graph = reachable_states(&ap, &start, 100);
+        assert!(graph.complete);
+        assert_eq!(4, graph.states.len());
+        assert_eq!(start, graph.states[0]);
+        // load, unload and shoot from both loaded states, load from both unloaded ones.
+        assert_eq!(8, graph.transitions.len());
+        assert!(graph.transitions.iter().any(|t| ap.name(t.action).as_ref() == "shoot" && t.cost == 2));
+
+        let bounded = reachable_states(&ap, &start, 2);
+        assert!(!bounded.complete);
+        assert_eq!(2, bounded.states.len());
+        assert!(bounded.transitions.iter().all(|t| t.from < 2 && t.to < 2));
+    }
+
+    #[test]
+    fn exports_graphs() {
+        let ap = planner();
+        let start = WorldState::builder(&ap).holds("armedwithgun").lacks("weaponloaded").build().unwrap();
+        let graph = reachable_states(&ap, &start, 100);
+
+        let dot = write_state_graph_dot(&graph, &ap);
+        assert!(dot.starts_with("digraph states {\n"));
+        assert!(dot.contains("n0 -> n1 [label=\"load (1)\"];"));
+
+        let graphml = write_state_graph_graphml(&graph, &ap);
+        assert!(graphml.contains("<edge id=\"e0\" source=\"n0\" target=\"n1\">"));
+        assert!(graphml.contains("<data key=\"action\">load</data>"));
+        assert_eq!(graph.states.len(), graphml.matches("<node ").count());
+    }
+}
@@ -0,0 +1,398 @@
+use ::goap::{WorldState, ActionPlanner, ActionId};
+use ::astar::{AStarPlan, Heuristic, SearchStats, mismatch};
+use ::graph::state_key;
+use ::builder::BuildError;
+use std::collections::{BTreeSet, HashMap};
+
+const MAX_NODES: usize = 2048;
+const INFINITY: i32 = i32::MAX;
+
+/// Index of the node standing for "any goal state". Every state that meets the goal has a
+/// free edge to it, so the search has a single target.
+const GOAL: usize = 0;
+
+type Key = (i32, i32);
+
+struct Node {
+    ws: WorldState,
+    g: i32,
+    rhs: i32,
+    /// Incoming edges, with `None` for the free edge into `GOAL`.
+    preds: Vec<(usize, Option<ActionId>)>,
+    /// Outgoing edges, filled in the first time the node is expanded.
+    succs: Option<Vec<(usize, Option<ActionId>)>>,
+    queued: Option<Key>
+}
+
+impl Node {
+    fn new(ws: WorldState) -> Self {
+        Node { ws, g: INFINITY, rhs: INFINITY, preds: Vec::new(), succs: None, queued: None }
+    }
+}
+
+fn add(a: i32, b: i32) -> i32 {
+    if a == INFINITY || b == INFINITY { INFINITY } else { a + b }
+}
+
+/// A planner for one goal that keeps its search between queries, so that after an action's
+/// cost changes only the part of the search the change affects is redone. This is Lifelong
+/// Planning A*.
+///
+/// Costs set with `update_cost` override those of the `ActionPlanner` for this planner only.
+/// The search runs forward, so every node's distance is measured from the start, and moving
+/// the start with `update_start` redoes the search. It keeps the states and edges found so
+/// far, which saves generating them again, but expands about as many nodes as a new planner
+/// would. Searching backward from the goal would keep start moves local, but the goal is a
+/// partial state met by too many states to search back from.
+pub struct IncrementalPlanner<'a> {
+    ap: &'a ActionPlanner,
+    goal: WorldState,
+    costs: Vec<i32>,
+    heuristic: Heuristic,
+    nodes: Vec<Node>,
+    index: HashMap<(u64, u64), usize>,
+    queue: BTreeSet<(Key, usize)>,
+    start: usize,
+    stats: SearchStats
+}
+
+impl<'a> IncrementalPlanner<'a> {
+    pub fn new(ap: &'a ActionPlanner, start: &WorldState, goal: &WorldState) -> Self {
+        let mut planner = IncrementalPlanner {
+            ap,
+            goal: *goal,
+            costs: ap.action_ids().map(|action| ap.cost(action)).collect(),
+            heuristic: Heuristic::default(),
+            nodes: vec![Node::new(*goal)],
+            index: HashMap::new(),
+            queue: BTreeSet::new(),
+            start: GOAL,
+            stats: SearchStats::default()
+        };
+        planner.start = planner.node(start);
+        planner.nodes[planner.start].rhs = 0;
+        planner.update_vertex(planner.start);
+        planner
+    }
+
+    /// Changes the heuristic. This throws away the search so far, since its queue is ordered
+    /// by the old one.
+    pub fn set_heuristic(&mut self, heuristic: Heuristic) {
+        self.heuristic = heuristic;
+        self.reset();
+    }
+
+    /// Throws away the search so far, keeping the start, goal and costs.
+    fn reset(&mut self) {
+        let start = self.nodes[self.start].ws;
+        self.nodes.truncate(1);
+        self.nodes[GOAL] = Node::new(self.goal);
+        self.index.clear();
+        self.queue.clear();
+        self.start = self.node(&start);
+        self.nodes[self.start].rhs = 0;
+        self.update_vertex(self.start);
+    }
+
+    /// The cost this planner currently uses for `action`.
+    pub fn cost(&self, action: ActionId) -> i32 {
+        self.costs[action.index()]
+    }
+
+    /// Counts for the last `plan` call. `expanded` is the work it took to bring the search up
+    /// to date, and is zero when nothing changed since the call before.
+    pub fn stats(&self) -> SearchStats {
+        self.stats
+    }
+
+    /// Makes `action` cost `cost` from now on. Negative costs are rejected, as by
+    /// `ActionBuilder::build`.
+    pub fn update_cost(&mut self, action: ActionId, cost: i32) -> Result<(), BuildError> {
+        if cost < 0 {
+            return Err(BuildError::NegativeCost { action: self.ap.name(action).to_string(), cost });
+        }
+        if self.costs[action.index()] == cost {
+            return Ok(());
+        }
+        self.costs[action.index()] = cost;
+        let affected: Vec<usize> = (0..self.nodes.len())
+            .filter(|&v| self.nodes[v].preds.iter().any(|&(_, a)| a == Some(action)))
+            .collect();
+        for v in affected {
+            self.update_vertex(v);
+        }
+        Ok(())
+    }
+
+    /// Plans from `start` from now on. Unless the start stays the same, this forgets every
+    /// distance but keeps the states and edges found so far.
+    pub fn update_start(&mut self, start: &WorldState) {
+        let start = self.node(start);
+        if start == self.start {
+            return;
+        }
+        self.start = start;
+        self.queue.clear();
+        for node in &mut self.nodes {
+            node.g = INFINITY;
+            node.rhs = INFINITY;
+            node.queued = None;
+        }
+        self.nodes[start].rhs = 0;
+        self.update_vertex(start);
+    }
+
+    /// Brings the search up to date and returns the cheapest plan from the current start to
+    /// the goal, or `None` if there is none or the search hit its node limit. Hitting the
+    /// limit throws the search away, so the next call starts over from the current start.
+    pub fn plan(&mut self) -> Option<AStarPlan> {
+        self.stats = SearchStats::default();
+        while let Some(&(key, u)) = self.queue.iter().next() {
+            // Ties are expanded too: the free edges into `GOAL` can give a node the same key
+            // as the goal it still has to update.
+            let goal = &self.nodes[GOAL];
+            if key > self.key(GOAL) && goal.g == goal.rhs {
+                break;
+            }
+            if self.nodes.len() >= MAX_NODES {
+                self.reset();
+                self.stats.overflowed = true;
+                return None;
+            }
+            self.queue.remove(&(key, u));
+            self.nodes[u].queued = None;
+            self.stats.expanded += 1;
+            let succs = self.successors(u);
+            if self.nodes[u].g > self.nodes[u].rhs {
+                self.nodes[u].g = self.nodes[u].rhs;
+            } else {
+                self.nodes[u].g = INFINITY;
+                self.update_vertex(u);
+            }
+            for (v, _) in succs {
+                self.update_vertex(v);
+            }
+            self.stats.max_open = self.stats.max_open.max(self.queue.len());
+        }
+
+        if self.nodes[GOAL].g == INFINITY {
+            return None;
+        }
+        let mut steps = Vec::new();
+        let mut v = GOAL;
+        while v != self.start && steps.len() <= self.nodes.len() {
+            let (p, action) = self.nodes[v].preds.iter()
+                .filter(|&&(p, _)| self.nodes[p].g != INFINITY)
+                .min_by_key(|&&(p, action)| add(self.nodes[p].g, self.edge_cost(action)))
+                .cloned()?;
+            if let Some(action) = action {
+                steps.push((action, self.nodes[v].ws));
+            }
+            v = p;
+        }
+        // Zero-cost edges can make the walk loop; give up rather than return a plan that does
+        // not begin at the start.
+        if v != self.start {
+            return None;
+        }
+
+        let mut plan = AStarPlan::new();
+        plan.entries.push_back(("root".into(), self.nodes[self.start].ws));
+        for (action, ws) in steps.into_iter().rev() {
+            plan.entries.push_back((self.ap.name(action).clone(), ws));
+            plan.cost += self.costs[action.index()];
+        }
+        Some(plan)
+    }
+
+    /// The node for `ws`, added if it is new.
+    fn node(&mut self, ws: &WorldState) -> usize {
+        let key = state_key(ws);
+        if let Some(&v) = self.index.get(&key) {
+            return v;
+        }
+        self.nodes.push(Node::new(*ws));
+        self.index.insert(key, self.nodes.len() - 1);
+        self.stats.generated += 1;
+        self.nodes.len() - 1
+    }
+
+    fn successors(&mut self, u: usize) -> Vec<(usize, Option<ActionId>)> {
+        if let Some(ref succs) = self.nodes[u].succs {
+            return succs.clone();
+        }
+        let mut succs = Vec::new();
+        if u != GOAL {
+            let ws = self.nodes[u].ws;
+            if ws.satisfies(&self.goal) {
+                succs.push((GOAL, None));
+            }
+            for action in self.ap.applicable_actions(&ws) {
+                // Actions that change nothing can never shorten a plan.
+                let v = self.node(&self.ap.apply(action, &ws));
+                if v != u {
+                    succs.push((v, Some(action)));
+                }
+            }
+            for &(v, action) in &succs {
+                self.nodes[v].preds.push((u, action));
+            }
+        }
+        self.nodes[u].succs = Some(succs.clone());
+        succs
+    }
+
+    fn edge_cost(&self, action: Option<ActionId>) -> i32 {
+        action.map_or(0, |action| self.costs[action.index()])
+    }
+
+    fn heuristic(&self, v: usize) -> i32 {
+        if v == GOAL {
+            return 0;
+        }
+        match self.heuristic {
+            Heuristic::Mismatch => mismatch(&self.nodes[v].ws, &self.goal).count_ones() as i32,
+            Heuristic::Zero => 0
+        }
+    }
+
+    fn key(&self, v: usize) -> Key {
+        let node = &self.nodes[v];
+        let best = node.g.min(node.rhs);
+        (add(best, self.heuristic(v)), best)
+    }
+
+    fn update_vertex(&mut self, v: usize) {
+        if v != self.start {
+            let rhs = self.nodes[v].preds.iter()
+                .map(|&(p, action)| add(self.nodes[p].g, self.edge_cost(action)))
+                .min()
+                .unwrap_or(INFINITY);
+            self.nodes[v].rhs = rhs;
+        }
+        if let Some(key) = self.nodes[v].queued.take() {
+            self.queue.remove(&(key, v));
+        }
+        if self.nodes[v].g != self.nodes[v].rhs {
+            let key = self.key(v);
+            self.queue.insert((key, v));
+            self.nodes[v].queued = Some(key);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::IncrementalPlanner;
+    use ::goap::{ActionPlanner, WorldState};
+    use ::astar::{AStar, Heuristic};
+    use ::builder::BuildError;
+    use ::test_domains::{ambush, ambush_start, enemy_dead, step_names};
+
+    fn planner() -> ActionPlanner {
+        let mut builder = ActionPlanner::builder();
+        ambush(&mut builder);
+        builder.action("approach").requires("enemyvisible").sets("nearenemy").build().unwrap();
+        builder.action("detonatebomb").cost(5).requires("armedwithbomb").requires("nearenemy")
+            .clears("alive").clears("enemyalive").build().unwrap();
+        builder.action("flee").requires("enemyvisible").clears("nearenemy").build().unwrap();
+        builder.build()
+    }
+
+    /// The ambush start, also carrying a bomb.
+    fn start(ap: &ActionPlanner) -> WorldState {
+        let mut start = ambush_start(ap);
+        for &(name, value) in &[("nearenemy", false), ("armedwithbomb", true), ("alive", true)] {
+            start.set(ap, name, value);
+        }
+        start
+    }
+
+    #[test]
+    fn matches_astar() {
+        let ap = planner();
+        let goal = enemy_dead(&ap);
+        for &heuristic in &[Heuristic::Mismatch, Heuristic::Zero] {
+            let mut incremental = IncrementalPlanner::new(&ap, &start(&ap), &goal);
+            incremental.set_heuristic(heuristic);
+            let plan = incremental.plan().unwrap();
+            assert_eq!(AStar::new().plan(&ap, &start(&ap), &goal).unwrap().cost(), plan.cost());
+            assert_eq!(Ok(()), ::replay::validate_plan(&ap, &start(&ap), &goal, &plan));
+            assert!(incremental.stats().expanded > 0);
+
+            incremental.plan().unwrap();
+            assert_eq!(0, incremental.stats().expanded);
+        }
+    }
+
+    #[test]
+    fn follows_cost_changes() {
+        let ap = planner();
+        let goal = enemy_dead(&ap);
+        let mut incremental = IncrementalPlanner::new(&ap, &start(&ap), &goal);
+        assert_eq!(vec!["scout", "load", "aim", "shoot"], step_names(&incremental.plan().unwrap()));
+
+        let shoot = ap.lookup_action("shoot").unwrap();
+        incremental.update_cost(shoot, 10).unwrap();
+        let plan = incremental.plan().unwrap();
+        assert_eq!(vec!["scout", "approach", "detonatebomb"], step_names(&plan));
+        assert_eq!(7, plan.cost());
+
+        incremental.update_cost(shoot, 1).unwrap();
+        assert_eq!(4, incremental.plan().unwrap().cost());
+        assert_eq!(1, incremental.cost(shoot));
+
+        assert_eq!(Err(BuildError::NegativeCost { action: "shoot".to_owned(), cost: -1 }),
+                   incremental.update_cost(shoot, -1));
+        assert_eq!(1, incremental.cost(shoot));
+    }
+
+    #[test]
+    fn follows_start_changes() {
+        let ap = planner();
+        let goal = enemy_dead(&ap);
+        let mut incremental = IncrementalPlanner::new(&ap, &start(&ap), &goal);
+        let plan = incremental.plan().unwrap();
+
+        // Move along the plan, as an agent carrying it out would.
+        let (_, next) = *plan.iter().nth(2).unwrap();
+        incremental.update_start(&next);
+        let rest = incremental.plan().unwrap();
+        assert_eq!(step_names(&plan)[2..], step_names(&rest)[..]);
+        assert_eq!(Ok(()), ::replay::validate_plan(&ap, &next, &goal, &rest));
+
+        // Redoing the search costs what a new planner would, less finding the states again.
+        let mut fresh = IncrementalPlanner::new(&ap, &next, &goal);
+        fresh.plan().unwrap();
+        assert_eq!(fresh.stats().expanded, incremental.stats().expanded);
+        assert_eq!(0, incremental.stats().generated);
+
+        let mut elsewhere = start(&ap);
+        elsewhere.set(&ap, "armedwithgun", false);
+        elsewhere.set(&ap, "armedwithbomb", false);
+        incremental.update_start(&elsewhere);
+        assert!(incremental.plan().is_none());
+    }
+
+    #[test]
+    fn starts_over_after_overflow() {
+        // Twelve switches give 4096 states, none of which reach the goal.
+        let mut builder = ActionPlanner::builder();
+        for i in 0..12 {
+            builder.action(&format!("flip{}", i)).sets(&format!("switch{}", i)).build().unwrap();
+        }
+        builder.register_atom("done");
+        let ap = builder.build();
+        let start = WorldState::builder(&ap).lacks("done").build().unwrap();
+        let goal = WorldState::builder(&ap).holds("done").build().unwrap();
+        let mut incremental = IncrementalPlanner::new(&ap, &start, &goal);
+        assert!(incremental.plan().is_none());
+        assert!(incremental.stats().overflowed);
+
+        incremental.update_start(&goal);
+        assert_eq!(0, incremental.plan().unwrap().cost());
+        assert!(!incremental.stats().overflowed);
+    }
+}
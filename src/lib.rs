@@ -1,8 +1,55 @@
-#[macro_use] extern crate itertools;
+extern crate itertools;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
+#[macro_use]
+mod macros;
 mod bitset;
 mod goap;
 mod astar;
+mod builder;
+mod diagnose;
+mod lint;
+mod prune;
+mod replay;
+mod execute;
+mod repair;
+mod incremental;
+mod cache;
+mod lifted;
+mod pddl;
+mod dsl;
+mod dot;
+mod graph;
+#[cfg(feature = "rayon")]
+mod batch;
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(test)]
+mod test_domains;
 
-pub use goap::{WorldState, WorldStateFmt, ActionPlanner};
-pub use astar::{AStarPlan, AStar};
\ No newline at end of file
+pub use goap::{MAX_ATOMS, WorldState, WorldStateFmt, ActionPlanner, ActionPlannerBuilder, Problem, AtomId, ActionId, Actions};
+pub use astar::{AStarPlan, AStar, Heuristic, SearchStats, StateTransIter};
+pub use builder::{BuildError, ActionBuilder, WorldStateBuilder};
+pub use diagnose::{Diagnosis, DiagnosisFmt};
+pub use lint::{Lint, LintFmt, validate, validate_problem};
+pub use prune::{Pruning, prune};
+pub use replay::{PlanValidationError, validate_plan};
+pub use execute::{ActionOutcome, ExecStatus, PlanExecutor};
+pub use repair::Repair;
+pub use incremental::IncrementalPlanner;
+pub use cache::PlanCache;
+pub use lifted::{LiftedDomain, GroundError, format_atom};
+pub use pddl::{PddlDomain, PddlError, parse_domain, parse_problem, write_pddl_domain, write_pddl_problem,
+               write_pddl_plan};
+pub use dsl::{DslError, Span, parse_dsl, write_dsl};
+pub use dot::{write_search_dot, write_state_graph_dot};
+pub use graph::{StateGraph, Transition, reachable_states, write_state_graph_graphml};
+#[cfg(feature = "rayon")]
+pub use batch::plan_batch;
+#[cfg(feature = "serde")]
+pub use serialization::{Named, NamedSeed};
@@ -0,0 +1,417 @@
+use ::goap::ActionPlannerBuilder;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum GroundError {
+    /// An atom template is not of the form `name` or `name(arg, ...)`.
+    MalformedAtom(String),
+    /// An atom template refers to a `?variable` that is not a parameter of its action.
+    UnboundVariable { action: String, variable: String },
+    /// A parameter was declared without a leading `?`.
+    BadParameter { action: String, variable: String },
+    /// Grounding needs more atoms than an `ActionPlanner` can hold.
+    TooManyAtoms
+}
+
+impl fmt::Display for GroundError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GroundError::MalformedAtom(ref atom) => write!(f, "malformed atom `{}`", atom),
+            GroundError::UnboundVariable { ref action, ref variable } =>
+                write!(f, "action `{}` uses unbound variable `{}`", action, variable),
+            GroundError::BadParameter { ref action, ref variable } =>
+                write!(f, "action `{}` parameter `{}` must start with `?`", action, variable),
+            GroundError::TooManyAtoms => write!(f, "grounded domain has more than the maximum number of atoms")
+        }
+    }
+}
+
+impl Error for GroundError {}
+
+#[derive(Clone)]
+struct ActionSchema {
+    name: String,
+    params: Vec<(String, String)>,
+    pre: Vec<(String, bool)>,
+    post: Vec<(String, bool)>,
+    cost: i32
+}
+
+impl ActionSchema {
+    fn new(name: &str) -> Self {
+        ActionSchema {
+            name: name.to_owned(),
+            params: Vec::new(),
+            pre: Vec::new(),
+            post: Vec::new(),
+            cost: 1
+        }
+    }
+}
+
+/// A domain of parameterised actions over typed objects.
+///
+/// Actions are declared once as schemas such as `pickup(?item: item)` and expanded by
+/// `ground` into one concrete `ActionPlannerBuilder` action per binding of their parameters,
+/// named like `pickup(axe)`. Atoms are written as `name` or `name(arg, ...)`, where each
+/// argument is either an object or one of the action's `?parameters`.
+///
+/// Types may be declared with a parent type; a parameter of some type is bound to objects of
+/// that type and of all its subtypes.
+#[derive(Default, Clone)]
+pub struct LiftedDomain {
+    types: Vec<(String, String)>,
+    objects: Vec<(String, String)>,
+    schemas: Vec<ActionSchema>
+}
+
+impl LiftedDomain {
+    pub fn new() -> Self {
+        LiftedDomain {
+            types: Vec::new(),
+            objects: Vec::new(),
+            schemas: Vec::new()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = LiftedDomain::new();
+    }
+
+    pub fn add_object(&mut self, name: &str, type_name: &str) {
+        if !self.objects.iter().any(|(n, _)| n == name) {
+            self.objects.push((name.to_owned(), type_name.to_owned()));
+        }
+    }
+
+    /// Declares `type_name` as a subtype of `parent`.
+    pub fn add_type(&mut self, type_name: &str, parent: &str) {
+        match self.types.iter().position(|(t, _)| t == type_name) {
+            Some(idx) => self.types[idx].1 = parent.to_owned(),
+            None => self.types.push((type_name.to_owned(), parent.to_owned()))
+        }
+    }
+
+    /// Whether `type_name` is `ancestor` or one of its declared subtypes.
+    pub fn is_subtype(&self, type_name: &str, ancestor: &str) -> bool {
+        let mut current = type_name;
+        // Bounded so that a cyclic declaration cannot loop forever.
+        for _ in 0..=self.types.len() {
+            if current == ancestor {
+                return true;
+            }
+            match self.types.iter().find(|(t, _)| t == current) {
+                Some((_, parent)) => current = parent,
+                None => return false
+            }
+        }
+        false
+    }
+
+    pub fn objects_of<'a>(&'a self, type_name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.objects.iter()
+            .filter(move |&(_, t)| self.is_subtype(t, type_name))
+            .map(|(n, _)| n.as_str())
+    }
+
+    pub fn object_type(&self, name: &str) -> Option<&str> {
+        self.objects.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, t)| t.as_str())
+    }
+
+    fn schema_mut(&mut self, action_name: &str) -> &mut ActionSchema {
+        let idx = match self.schemas.iter().position(|s| s.name == action_name) {
+            Some(idx) => idx,
+            None => {
+                self.schemas.push(ActionSchema::new(action_name));
+                self.schemas.len() - 1
+            }
+        };
+        &mut self.schemas[idx]
+    }
+
+    pub fn has_schema(&self, action_name: &str) -> bool {
+        self.schemas.iter().any(|s| s.name == action_name)
+    }
+
+    pub fn add_param(&mut self, action_name: &str, variable: &str, type_name: &str) -> bool {
+        let schema = self.schema_mut(action_name);
+        if schema.params.iter().any(|(v, _)| v == variable) {
+            return false;
+        }
+        schema.params.push((variable.to_owned(), type_name.to_owned()));
+        true
+    }
+
+    pub fn set_pre(&mut self, action_name: &str, atom: &str, value: bool) {
+        self.schema_mut(action_name).pre.push((atom.to_owned(), value));
+    }
+
+    pub fn set_post(&mut self, action_name: &str, atom: &str, value: bool) {
+        self.schema_mut(action_name).post.push((atom.to_owned(), value));
+    }
+
+    pub fn set_cost(&mut self, action_name: &str, cost: i32) {
+        self.schema_mut(action_name).cost = cost;
+    }
+
+    /// Adds every grounding of every action schema to `ap`, returning how many concrete
+    /// actions were added.
+    ///
+    /// Parameters may be bound to the same object, which can make two literals name one
+    /// atom. A grounding whose preconditions then need an atom both true and false can never
+    /// apply and is left out. When its effects both delete and add an atom the add wins, as
+    /// in PDDL.
+    pub fn ground(&self, ap: &mut ActionPlannerBuilder) -> Result<usize, GroundError> {
+        let mut count = 0;
+        for schema in &self.schemas {
+            let mut candidates = Vec::with_capacity(schema.params.len());
+            for (variable, type_name) in &schema.params {
+                if !variable.starts_with('?') {
+                    return Err(GroundError::BadParameter {
+                        action: schema.name.clone(),
+                        variable: variable.clone()
+                    });
+                }
+                candidates.push(self.objects_of(type_name).collect::<Vec<_>>());
+            }
+            if candidates.iter().any(|c| c.is_empty()) {
+                continue;
+            }
+
+            let mut choice = vec![0; candidates.len()];
+            loop {
+                let binding: Vec<&str> = choice.iter()
+                    .zip(candidates.iter())
+                    .map(|(&i, c)| c[i])
+                    .collect();
+                if self.ground_action(schema, &binding, ap)? {
+                    count += 1;
+                }
+
+                // Advance the binding like an odometer, last parameter fastest.
+                let mut pos = choice.len();
+                while pos > 0 {
+                    pos -= 1;
+                    choice[pos] += 1;
+                    if choice[pos] < candidates[pos].len() {
+                        break;
+                    }
+                    choice[pos] = 0;
+                }
+                if choice.iter().all(|&i| i == 0) {
+                    break;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Adds the grounding of `schema` for `binding` to `ap`, returning false if its
+    /// preconditions contradict each other and it was left out.
+    fn ground_action(&self, schema: &ActionSchema, binding: &[&str], ap: &mut ActionPlannerBuilder)
+                     -> Result<bool, GroundError> {
+        let mut pre: Vec<(String, bool)> = Vec::with_capacity(schema.pre.len());
+        for &(ref template, value) in &schema.pre {
+            let atom = ground_atom(schema, template, binding)?;
+            if pre.iter().any(|(a, v)| *a == atom && *v != value) {
+                return Ok(false);
+            }
+            pre.push((atom, value));
+        }
+        let mut post = Vec::with_capacity(schema.post.len());
+        for &(ref template, value) in &schema.post {
+            post.push((ground_atom(schema, template, binding)?, value));
+        }
+        // Deletes go first so that an atom both deleted and added ends up true.
+        post.sort_by_key(|&(_, value)| value);
+
+        let action = format_atom(&schema.name, binding);
+        ap.set_cost(&action, schema.cost);
+        for (atom, value) in pre {
+            if !ap.set_pre(&action, &atom, value) {
+                return Err(GroundError::TooManyAtoms);
+            }
+        }
+        for (atom, value) in post {
+            if !ap.set_post(&action, &atom, value) {
+                return Err(GroundError::TooManyAtoms);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Formats a grounded atom or action name, e.g. `at(axe, shed)`.
+pub fn format_atom<S: AsRef<str>>(name: &str, args: &[S]) -> String {
+    if args.is_empty() {
+        return name.to_owned();
+    }
+    let args: Vec<&str> = args.iter().map(|a| a.as_ref()).collect();
+    format!("{}({})", name, args.join(", "))
+}
+
+/// Splits `name(arg, ...)` into its name and arguments.
+pub(crate) fn parse_atom(template: &str) -> Option<(&str, Vec<&str>)> {
+    let template = template.trim();
+    match template.find('(') {
+        None if !template.is_empty() => Some((template, Vec::new())),
+        Some(open) if template.ends_with(')') => {
+            let name = template[..open].trim();
+            let inner = template[open + 1..template.len() - 1].trim();
+            let args: Vec<&str> = match inner.is_empty() {
+                true => Vec::new(),
+                false => inner.split(',').map(|a| a.trim()).collect()
+            };
+            if name.is_empty() || args.iter().any(|a| a.is_empty()) {
+                None
+            } else {
+                Some((name, args))
+            }
+        },
+        _ => None
+    }
+}
+
+fn ground_atom(schema: &ActionSchema, template: &str, binding: &[&str]) -> Result<String, GroundError> {
+    let (name, args) = parse_atom(template)
+        .ok_or_else(|| GroundError::MalformedAtom(template.to_owned()))?;
+    let mut grounded = Vec::with_capacity(args.len());
+    for arg in args {
+        if arg.starts_with('?') {
+            match schema.params.iter().position(|(v, _)| v == arg) {
+                Some(idx) => grounded.push(binding[idx]),
+                None => return Err(GroundError::UnboundVariable {
+                    action: schema.name.clone(),
+                    variable: arg.to_owned()
+                })
+            }
+        } else {
+            grounded.push(arg);
+        }
+    }
+    Ok(format_atom(name, &grounded))
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{LiftedDomain, GroundError};
+    use ::goap::{ActionPlanner, WorldState};
+    use ::astar::AStar;
+    use ::test_domains::step_names;
+
+    fn armory() -> LiftedDomain {
+        let mut domain = LiftedDomain::new();
+        domain.add_object("axe", "item");
+        domain.add_object("sword", "item");
+        domain.add_object("shed", "place");
+
+        domain.add_param("pickup", "?item", "item");
+        domain.set_pre("pickup", "onground(?item)", true);
+        domain.set_pre("pickup", "handsfree", true);
+        domain.set_post("pickup", "holding(?item)", true);
+        domain.set_post("pickup", "onground(?item)", false);
+        domain.set_post("pickup", "handsfree", false);
+
+        domain.add_param("stash", "?item", "item");
+        domain.add_param("stash", "?place", "place");
+        domain.set_pre("stash", "holding(?item)", true);
+        domain.set_post("stash", "holding(?item)", false);
+        domain.set_post("stash", "in(?item, ?place)", true);
+        domain.set_post("stash", "handsfree", true);
+        domain.set_cost("stash", 2);
+        domain
+    }
+
+    #[test]
+    fn grounds_every_binding() {
+        let mut builder = ActionPlanner::builder();
+        assert_eq!(Ok(4), armory().ground(&mut builder));
+        let ap = builder.build();
+        let (names, _, costs, _) = ap.actions();
+        let names: Vec<&str> = names.iter().map(|n| &**n).collect();
+        assert_eq!(vec!["pickup(axe)", "pickup(sword)", "stash(axe, shed)", "stash(sword, shed)"], names);
+        assert_eq!(&[1, 1, 2, 2], costs);
+    }
+
+    #[test]
+    fn plans_with_grounded_names() {
+        let mut builder = ActionPlanner::builder();
+        armory().ground(&mut builder).unwrap();
+        let ap = builder.build();
+
+        let mut start = WorldState::new();
+        start.set(&ap, "onground(axe)", true);
+        start.set(&ap, "onground(sword)", true);
+        start.set(&ap, "handsfree", true);
+        let mut goal = WorldState::new();
+        goal.set(&ap, "in(sword, shed)", true);
+
+        let plan = AStar::new().plan(&ap, &start, &goal).unwrap();
+        assert_eq!(vec!["pickup(sword)", "stash(sword, shed)"], step_names(&plan));
+        assert_eq!(3, plan.cost());
+    }
+
+    #[test]
+    fn skips_contradictory_bindings() {
+        let mut domain = LiftedDomain::new();
+        domain.add_object("axe", "item");
+        domain.add_object("sword", "item");
+        domain.add_param("swap", "?from", "item");
+        domain.add_param("swap", "?to", "item");
+        domain.set_pre("swap", "holding(?from)", true);
+        domain.set_pre("swap", "holding(?to)", false);
+        domain.set_post("swap", "holding(?to)", true);
+        domain.set_post("swap", "holding(?from)", false);
+
+        let mut builder = ActionPlanner::builder();
+        assert_eq!(Ok(2), domain.ground(&mut builder));
+        let ap = builder.build();
+        let (names, _, _, _) = ap.actions();
+        let names: Vec<&str> = names.iter().map(|n| &**n).collect();
+        assert_eq!(vec!["swap(axe, sword)", "swap(sword, axe)"], names);
+    }
+
+    #[test]
+    fn adds_win_over_deletes() {
+        let mut domain = LiftedDomain::new();
+        domain.add_object("axe", "item");
+        domain.add_param("juggle", "?a", "item");
+        domain.add_param("juggle", "?b", "item");
+        domain.set_post("juggle", "holding(?a)", true);
+        domain.set_post("juggle", "holding(?b)", false);
+
+        let mut builder = ActionPlanner::builder();
+        domain.ground(&mut builder).unwrap();
+        let ap = builder.build();
+        let juggle = ap.lookup_action("juggle(axe, axe)").unwrap();
+        assert_eq!(Some(true), ap.post(juggle).get(ap.lookup_atom("holding(axe)").unwrap()));
+    }
+
+    #[test]
+    fn rejects_unbound_variables() {
+        let mut domain = LiftedDomain::new();
+        domain.add_object("axe", "item");
+        domain.add_param("drop", "?item", "item");
+        domain.set_post("drop", "onground(?thing)", true);
+        let err = domain.ground(&mut ActionPlanner::builder()).unwrap_err();
+        assert_eq!(GroundError::UnboundVariable { action: "drop".to_owned(), variable: "?thing".to_owned() }, err);
+    }
+
+    #[test]
+    fn binds_subtypes() {
+        let mut domain = LiftedDomain::new();
+        domain.add_type("weapon", "item");
+        domain.add_type("sword", "weapon");
+        domain.add_object("rope", "item");
+        domain.add_object("excalibur", "sword");
+        domain.add_object("bow", "weapon");
+        assert_eq!(vec!["rope", "excalibur", "bow"], domain.objects_of("item").collect::<Vec<_>>());
+        assert_eq!(vec!["excalibur", "bow"], domain.objects_of("weapon").collect::<Vec<_>>());
+        assert!(!domain.is_subtype("item", "weapon"));
+    }
+}
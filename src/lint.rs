@@ -0,0 +1,239 @@
+use ::goap::{ActionPlanner, AtomId, ActionId, Problem};
+use ::bitset::BitSetU64;
+use ::prune::reachability;
+use std::fmt;
+
+/// A likely mistake in a domain, found by `validate` or `validate_problem`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Lint {
+    /// Every effect of the action is already required by its preconditions, so applying it
+    /// changes nothing.
+    NoOp { action: ActionId },
+    /// No action reads or sets the atom.
+    UnusedAtom { atom: AtomId },
+    /// Actions require the atom but none sets it, so only the start state decides it.
+    ReadOnlyAtom { atom: AtomId },
+    /// The action requires `atom` to be `value` and sets it the other way, and no action sets
+    /// it back, so the action can apply at most once. This is often intended, as when shooting
+    /// the enemy dead, so it is only a note.
+    OneShot { action: ActionId, atom: AtomId, value: bool },
+    /// The action has the same preconditions and effects as an earlier one.
+    Duplicate { action: ActionId, original: ActionId },
+    /// The action costs zero or less, which A* assumes never happens.
+    NonPositiveCost { action: ActionId, cost: i32 },
+    /// The action requires `atom` to be `value`, but the start does not supply that value
+    /// and no action applicable from the start produces it, so the action can never apply.
+    /// Only `validate_problem` reports this.
+    NeverApplicable { action: ActionId, atom: AtomId, value: bool }
+}
+
+/// Checks `ap` for actions and atoms that are probably content bugs. Lints are returned
+/// grouped by kind, in action or atom order.
+///
+/// Any precondition holds in some start state, so without one this cannot tell that an
+/// action never applies; `validate_problem` can.
+pub fn validate(ap: &ActionPlanner) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let mut read = BitSetU64::empty();
+    let mut written = BitSetU64::empty();
+    let mut set_true = BitSetU64::empty();
+    let mut set_false = BitSetU64::empty();
+    for action in ap.action_ids() {
+        let (pre, post) = (ap.pre(action), ap.post(action));
+        read |= pre.cares();
+        written |= post.cares();
+        set_true |= post.cares() & post.values;
+        set_false |= post.cares() & (post.values ^ BitSetU64::full());
+    }
+
+    for action in ap.action_ids() {
+        let (pre, post) = (ap.pre(action), ap.post(action));
+        if pre.overlay(post) == *pre {
+            lints.push(Lint::NoOp { action });
+        }
+    }
+    for atom in ap.atom_ids() {
+        let i = atom.index();
+        if !read.get(i) && !written.get(i) {
+            lints.push(Lint::UnusedAtom { atom });
+        } else if !written.get(i) {
+            lints.push(Lint::ReadOnlyAtom { atom });
+        }
+    }
+    for action in ap.action_ids() {
+        let (pre, post) = (ap.pre(action), ap.post(action));
+        let undone = pre.cares() & post.cares() & (pre.values ^ post.values);
+        for atom in ap.atom_ids().filter(|atom| undone.get(atom.index())) {
+            let value = pre.values.get(atom.index());
+            let produced = if value { &set_true } else { &set_false };
+            if !produced.get(atom.index()) {
+                lints.push(Lint::OneShot { action, atom, value });
+            }
+        }
+    }
+    for action in ap.action_ids() {
+        let original = ap.action_ids()
+            .take_while(|&other| other != action)
+            .find(|&other| ap.pre(other).diff(ap.pre(action)).next().is_none()
+                  && ap.post(other).diff(ap.post(action)).next().is_none());
+        if let Some(original) = original {
+            lints.push(Lint::Duplicate { action, original });
+        }
+    }
+    for action in ap.action_ids() {
+        let cost = ap.cost(action);
+        if cost <= 0 {
+            lints.push(Lint::NonPositiveCost { action, cost });
+        }
+    }
+    lints
+}
+
+/// Runs `validate` on the problem's planner, then adds a `NeverApplicable` warning for
+/// each action that cannot apply from the problem's start whatever is done first.
+///
+/// Reachability ignores delete effects, so an action is only flagged when a needed value is
+/// out of reach even then. Atoms the start leaves unspecified may take either value.
+pub fn validate_problem(problem: &Problem) -> Vec<Lint> {
+    let ap = &problem.planner;
+    let mut lints = validate(ap);
+    let (reachable, can_be_true, can_be_false) = reachability(ap, &problem.start);
+    for action in ap.action_ids().filter(|action| !reachable[action.index()]) {
+        let pre = ap.pre(action);
+        let missing = (pre.values & pre.cares() & (can_be_true ^ BitSetU64::full()))
+            | ((pre.values ^ BitSetU64::full()) & pre.cares() & (can_be_false ^ BitSetU64::full()));
+        if let Some(atom) = ap.atom_ids().find(|atom| missing.get(atom.index())) {
+            lints.push(Lint::NeverApplicable { action, atom, value: pre.values.get(atom.index()) });
+        }
+    }
+    lints
+}
+
+impl Lint {
+    /// Whether the lint is likely a mistake, rather than a note about something that is
+    /// often intended.
+    pub fn is_warning(&self) -> bool {
+        !matches!(*self, Lint::OneShot { .. })
+    }
+
+    pub fn display<'a, 'b>(&'a self, ap: &'b ActionPlanner) -> LintFmt<'a, 'b> {
+        LintFmt { lint: self, ap }
+    }
+}
+
+/// Writes a `Lint` with atoms and actions by name.
+pub struct LintFmt<'a, 'b> {
+    lint: &'a Lint,
+    ap: &'b ActionPlanner
+}
+
+impl<'a, 'b> fmt::Display for LintFmt<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ap = self.ap;
+        match *self.lint {
+            Lint::NoOp { action } =>
+                write!(f, "action `{}` changes nothing its preconditions do not already require", ap.name(action)),
+            Lint::UnusedAtom { atom } => write!(f, "atom `{}` is never read or set", ap.atom_name(atom)),
+            Lint::ReadOnlyAtom { atom } => write!(f, "atom `{}` is read but never set", ap.atom_name(atom)),
+            Lint::OneShot { action, atom, value } =>
+                write!(f, "action `{}` can apply only once: it sets `{}` to {} and no action sets it back",
+                       ap.name(action), ap.atom_name(atom), !value),
+            Lint::Duplicate { action, original } =>
+                write!(f, "action `{}` duplicates `{}`", ap.name(action), ap.name(original)),
+            Lint::NonPositiveCost { action, cost } =>
+                write!(f, "action `{}` has cost {}; A* needs positive costs", ap.name(action), cost),
+            Lint::NeverApplicable { action, atom, value } =>
+                write!(f, "action `{}` can never apply: it requires `{}` to be {}, which nothing makes it",
+                       ap.name(action), ap.atom_name(atom), value)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{validate, validate_problem, Lint};
+    use ::goap::{ActionPlanner, Problem, WorldState};
+
+    #[test]
+    fn flags_suspicious_content() {
+        let mut builder = ActionPlanner::builder();
+        builder.action("load").requires("armedwithgun").sets("weaponloaded").build().unwrap();
+        builder.action("reload").requires("armedwithgun").sets("weaponloaded").build().unwrap();
+        builder.action("idle").requires("weaponloaded").sets("weaponloaded").build().unwrap();
+        builder.action("shoot").cost(0).requires("enemyalive").clears("enemyalive").build().unwrap();
+        builder.register_atom("alive");
+        let ap = builder.build();
+        let action = |name| ap.lookup_action(name).unwrap();
+        let atom = |name| ap.lookup_atom(name).unwrap();
+
+        assert_eq!(vec![
+            Lint::NoOp { action: action("idle") },
+            Lint::ReadOnlyAtom { atom: atom("armedwithgun") },
+            Lint::UnusedAtom { atom: atom("alive") },
+            Lint::OneShot { action: action("shoot"), atom: atom("enemyalive"), value: true },
+            Lint::Duplicate { action: action("reload"), original: action("load") },
+            Lint::NonPositiveCost { action: action("shoot"), cost: 0 }
+        ], validate(&ap));
+
+        let lint = Lint::Duplicate { action: action("reload"), original: action("load") };
+        assert_eq!("action `reload` duplicates `load`", lint.display(&ap).to_string());
+        assert!(lint.is_warning());
+        let lint = Lint::OneShot { action: action("shoot"), atom: atom("enemyalive"), value: true };
+        assert!(!lint.is_warning());
+    }
+
+    #[test]
+    fn notes_only_actions_nothing_undoes() {
+        let mut builder = ActionPlanner::builder();
+        builder.action("load").requires("armedwithgun").forbids("weaponloaded").sets("weaponloaded").build().unwrap();
+        builder.action("shoot").requires("weaponloaded").clears("weaponloaded").clears("enemyalive").build().unwrap();
+        builder.action("drop").requires("armedwithgun").clears("armedwithgun").build().unwrap();
+        let ap = builder.build();
+        let action = |name| ap.lookup_action(name).unwrap();
+        let atom = |name| ap.lookup_atom(name).unwrap();
+        assert_eq!(vec![Lint::OneShot { action: action("drop"), atom: atom("armedwithgun"), value: true }],
+                   validate(&ap));
+    }
+
+    #[test]
+    fn accepts_clean_domains() {
+        let mut builder = ActionPlanner::builder();
+        builder.action("pickup").forbids("armedwithgun").sets("armedwithgun").build().unwrap();
+        builder.action("drop").requires("armedwithgun").clears("armedwithgun").build().unwrap();
+        assert_eq!(Vec::<Lint>::new(), validate(&builder.build()));
+    }
+
+    #[test]
+    fn warns_about_actions_that_never_apply() {
+        let mut builder = ActionPlanner::builder();
+        builder.action("load").requires("armedwithgun").sets("weaponloaded").build().unwrap();
+        builder.action("shoot").requires("weaponloaded").clears("enemyalive").build().unwrap();
+        builder.action("prime").requires("armedwithbomb").sets("bombprimed").build().unwrap();
+        builder.action("detonate").requires("bombprimed").clears("enemyalive").build().unwrap();
+        let planner = builder.build();
+        let start = WorldState::builder(&planner)
+            .holds("armedwithgun").lacks("weaponloaded").lacks("armedwithbomb").lacks("bombprimed")
+            .holds("enemyalive")
+            .build().unwrap();
+        let problem = Problem { planner, start, goal: WorldState::new() };
+        let ap = &problem.planner;
+        let action = |name| ap.lookup_action(name).unwrap();
+        let atom = |name| ap.lookup_atom(name).unwrap();
+
+        let mut expected = validate(ap);
+        expected.push(Lint::NeverApplicable { action: action("prime"), atom: atom("armedwithbomb"), value: true });
+        expected.push(Lint::NeverApplicable { action: action("detonate"), atom: atom("bombprimed"), value: true });
+        assert_eq!(expected, validate_problem(&problem));
+        assert!(expected.last().unwrap().is_warning());
+        assert_eq!("action `detonate` can never apply: it requires `bombprimed` to be true, which nothing makes it",
+                   expected.last().unwrap().display(ap).to_string());
+
+        // Left unspecified, the bomb may be there from the start.
+        let bomb = atom("armedwithbomb");
+        let mut problem = problem;
+        problem.start.unset(bomb);
+        assert_eq!(validate(&problem.planner), validate_problem(&problem));
+    }
+}
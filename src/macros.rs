@@ -0,0 +1,138 @@
+/// Defines a domain at compile time.
+///
+/// Expands, in the invoking module, to an `atoms` module holding one `AtomId` constant per
+/// atom, an `actions` module holding one `ActionId` constant per action, and a `planner()`
+/// function building the matching `ActionPlanner`. Actions refer to atoms through those
+/// constants, so a misspelt atom is a compile error instead of a new atom. Actions cost 1
+/// unless given a `cost`.
+///
+/// ```
+/// #[macro_use]
+/// extern crate gpgoap;
+///
+/// mod combat {
+///     domain! {
+///         atoms { armedwithgun, weaponloaded, enemyalive }
+///         actions {
+///             load { pre { armedwithgun: true } post { weaponloaded: true } }
+///             shoot cost 2 { pre { weaponloaded: true } post { enemyalive: false } }
+///         }
+///     }
+/// }
+///
+/// fn main() {
+///     let ap = combat::planner();
+///     assert_eq!(2, ap.cost(combat::actions::shoot));
+///     let mut start = gpgoap::WorldState::new();
+///     start.set_id(combat::atoms::armedwithgun, true);
+///     start.set_id(combat::atoms::enemyalive, true);
+/// }
+/// ```
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate gpgoap;
+///
+/// domain! {
+///     atoms { weaponloaded, enemyalive }
+///     actions {
+///         shoot { pre { weaponlaoded: true } post { enemyalive: false } }
+///     }
+/// }
+///
+/// fn main() {}
+/// ```
+#[macro_export]
+macro_rules! domain {
+    (
+        atoms { $($atom:ident),* $(,)? }
+        actions {
+            $($action:ident $(cost $cost:tt)? {
+                $(pre { $($pre:ident : $pre_value:expr),* $(,)? })?
+                $(post { $($post:ident : $post_value:expr),* $(,)? })?
+            })*
+        }
+    ) => {
+        #[allow(non_camel_case_types, dead_code)]
+        enum __DomainAtom { $($atom,)* __Count }
+
+        #[allow(non_camel_case_types, dead_code)]
+        enum __DomainAction { $($action,)* }
+
+        const _: () = assert!(__DomainAtom::__Count as usize <= $crate::MAX_ATOMS, "too many atoms");
+
+        #[allow(non_upper_case_globals, dead_code)]
+        pub mod atoms {
+            $(pub const $atom: $crate::AtomId = $crate::AtomId::from_index(super::__DomainAtom::$atom as usize);)*
+        }
+
+        #[allow(non_upper_case_globals, dead_code)]
+        pub mod actions {
+            $(pub const $action: $crate::ActionId =
+                $crate::ActionId::from_index(super::__DomainAction::$action as usize);)*
+        }
+
+        /// Builds the planner for this domain.
+        #[allow(dead_code)]
+        pub fn planner() -> $crate::ActionPlanner {
+            let mut builder = $crate::ActionPlanner::builder();
+            $(builder.register_atom(stringify!($atom));)*
+            $(
+                let action = builder.register_action(stringify!($action)).unwrap();
+                debug_assert_eq!(actions::$action, action);
+                $(builder.set_cost_id(action, $cost);)?
+                $($(builder.set_pre_id(action, atoms::$pre, $pre_value);)*)?
+                $($(builder.set_post_id(action, atoms::$post, $post_value);)*)?
+            )*
+            builder.build()
+        }
+    };
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use ::goap::WorldState;
+    use ::astar::AStar;
+
+    mod combat {
+        domain! {
+            atoms { armedwithgun, weaponloaded, enemyalive, alive }
+            actions {
+                load { pre { armedwithgun: true } post { weaponloaded: true } }
+                shoot cost 2 {
+                    pre { weaponloaded: true, alive: true }
+                    post { enemyalive: false }
+                }
+                pickup { post { armedwithgun: true } }
+            }
+        }
+    }
+
+    #[test]
+    fn constants_match_planner() {
+        let ap = combat::planner();
+        assert_eq!(Some(combat::atoms::enemyalive), ap.lookup_atom("enemyalive"));
+        assert_eq!(Some(combat::actions::pickup), ap.lookup_action("pickup"));
+        assert_eq!(2, ap.cost(combat::actions::shoot));
+        assert_eq!(1, ap.cost(combat::actions::load));
+    }
+
+    #[test]
+    fn plans_with_typed_atoms() {
+        let ap = combat::planner();
+        let mut start = WorldState::new();
+        start.set_id(combat::atoms::armedwithgun, false);
+        start.set_id(combat::atoms::weaponloaded, false);
+        start.set_id(combat::atoms::enemyalive, true);
+        start.set_id(combat::atoms::alive, true);
+        let mut goal = WorldState::new();
+        goal.set_id(combat::atoms::enemyalive, false);
+
+        let plan = AStar::new().plan(&ap, &start, &goal).unwrap();
+        let names: Vec<&str> = plan.iter().map(|(name, _)| &**name).collect();
+        assert_eq!(vec!["root", "pickup", "load", "shoot"], names);
+        assert_eq!(4, plan.cost());
+    }
+}
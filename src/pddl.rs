@@ -0,0 +1,774 @@
+use ::goap::{ActionPlanner, WorldState, Problem};
+use ::astar::AStarPlan;
+use ::lifted::{LiftedDomain, GroundError, format_atom};
+use itertools::multizip;
+use std::error::Error;
+use std::fmt;
+
+const SUPPORTED_REQUIREMENTS: [&str; 4] = [":strips", ":typing", ":negative-preconditions", ":action-costs"];
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PddlError {
+    /// The input is not valid PDDL or uses something outside the supported STRIPS subset.
+    /// `line` and `column` are 1-based.
+    Parse { line: usize, column: usize, message: String },
+    /// The parsed problem could not be grounded into an `ActionPlanner`. The position is in
+    /// the problem source: its `:objects`, or its `:goal` when the goal's atoms do not fit.
+    Ground { line: usize, column: usize, error: GroundError }
+}
+
+impl fmt::Display for PddlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PddlError::Parse { line, column, ref message } => write!(f, "{}:{}: {}", line, column, message),
+            PddlError::Ground { line, column, ref error } => write!(f, "{}:{}: {}", line, column, error)
+        }
+    }
+}
+
+impl Error for PddlError {}
+
+#[derive(Clone, Copy)]
+struct Pos {
+    line: usize,
+    column: usize
+}
+
+fn error<T>(pos: Pos, message: String) -> Result<T, PddlError> {
+    Err(PddlError::Parse { line: pos.line, column: pos.column, message })
+}
+
+enum Sexp {
+    Symbol(String, Pos),
+    List(Vec<Sexp>, Pos)
+}
+
+impl Sexp {
+    fn pos(&self) -> Pos {
+        match *self {
+            Sexp::Symbol(_, pos) | Sexp::List(_, pos) => pos
+        }
+    }
+
+    fn symbol(&self) -> Option<&str> {
+        match *self {
+            Sexp::Symbol(ref s, _) => Some(s),
+            Sexp::List(..) => None
+        }
+    }
+
+    fn expect_symbol(&self, what: &str) -> Result<&str, PddlError> {
+        match *self {
+            Sexp::Symbol(ref s, _) => Ok(s),
+            Sexp::List(_, pos) => error(pos, format!("expected {}, found a list", what))
+        }
+    }
+
+    fn expect_list(&self, what: &str) -> Result<&[Sexp], PddlError> {
+        match *self {
+            Sexp::List(ref items, _) => Ok(items),
+            Sexp::Symbol(ref s, pos) => error(pos, format!("expected {}, found `{}`", what, s))
+        }
+    }
+
+    /// The head symbol of a list, e.g. `and` for `(and ...)`.
+    fn head(&self) -> Option<&str> {
+        match *self {
+            Sexp::List(ref items, _) => items.first().and_then(Sexp::symbol),
+            Sexp::Symbol(..) => None
+        }
+    }
+}
+
+/// Reads the single top-level s-expression of `src`. Symbols are lowercased as PDDL is
+/// case-insensitive, and `;` starts a comment that runs to the end of the line.
+fn read_sexp(src: &str) -> Result<Sexp, PddlError> {
+    let mut stack: Vec<(Vec<Sexp>, Pos)> = Vec::new();
+    let mut top = None;
+    let mut symbol: Option<(String, Pos)> = None;
+    let mut in_comment = false;
+    let mut pos = Pos { line: 1, column: 1 };
+
+    for c in src.chars() {
+        let here = pos;
+        if c == '\n' {
+            pos.line += 1;
+            pos.column = 1;
+        } else {
+            pos.column += 1;
+        }
+        if in_comment {
+            in_comment = c != '\n';
+            continue;
+        }
+
+        let delimiter = c.is_whitespace() || c == '(' || c == ')' || c == ';';
+        if !delimiter {
+            match symbol {
+                Some((ref mut s, _)) => s.extend(c.to_lowercase()),
+                None => symbol = Some((c.to_lowercase().collect(), here))
+            }
+            continue;
+        }
+        if let Some((s, start)) = symbol.take() {
+            push_sexp(&mut stack, &mut top, Sexp::Symbol(s, start))?;
+        }
+        match c {
+            ';' => in_comment = true,
+            '(' => stack.push((Vec::new(), here)),
+            ')' => match stack.pop() {
+                Some((items, start)) => push_sexp(&mut stack, &mut top, Sexp::List(items, start))?,
+                None => return error(here, "unexpected `)`".to_owned())
+            },
+            _ => {}
+        }
+    }
+    if let Some((s, start)) = symbol.take() {
+        push_sexp(&mut stack, &mut top, Sexp::Symbol(s, start))?;
+    }
+    if let Some(&(_, start)) = stack.last() {
+        return error(start, "unclosed `(`".to_owned());
+    }
+    match top {
+        Some(sexp) => Ok(sexp),
+        None => error(pos, "expected `(define ...)`".to_owned())
+    }
+}
+
+fn push_sexp(stack: &mut [(Vec<Sexp>, Pos)], top: &mut Option<Sexp>, sexp: Sexp) -> Result<(), PddlError> {
+    match stack.last_mut() {
+        Some((items, _)) => items.push(sexp),
+        None if top.is_none() => *top = Some(sexp),
+        None => return error(sexp.pos(), "unexpected input after `(define ...)`".to_owned())
+    }
+    Ok(())
+}
+
+/// Checks the `(define (<kind> <name>) ...)` header and returns the name and the sections.
+fn read_define<'a>(root: &'a Sexp, kind: &str) -> Result<(&'a str, &'a [Sexp]), PddlError> {
+    let items = root.expect_list("`(define ...)`")?;
+    if items.first().and_then(Sexp::symbol) != Some("define") {
+        return error(root.pos(), "expected `(define ...)`".to_owned());
+    }
+    let header = match items.get(1) {
+        Some(header) => header.expect_list(&format!("`({} <name>)`", kind))?,
+        None => return error(root.pos(), format!("expected `({} <name>)`", kind))
+    };
+    match (header.first().and_then(Sexp::symbol), header.get(1)) {
+        (Some(k), Some(name)) if k == kind && header.len() == 2 =>
+            Ok((name.expect_symbol("a name")?, &items[2..])),
+        _ => error(items[1].pos(), format!("expected `({} <name>)`", kind))
+    }
+}
+
+/// Reads a typed list such as `a b - block c`, where untyped names are `object`s.
+fn read_typed_list(items: &[Sexp]) -> Result<Vec<(String, String, Pos)>, PddlError> {
+    let mut typed = Vec::new();
+    let mut pending: Vec<(String, Pos)> = Vec::new();
+    let mut iter = items.iter();
+    while let Some(item) = iter.next() {
+        let name = item.expect_symbol("a name")?;
+        if name != "-" {
+            pending.push((name.to_owned(), item.pos()));
+            continue;
+        }
+        let type_name = match iter.next() {
+            Some(Sexp::Symbol(t, _)) => t,
+            Some(other) => return error(other.pos(), "only simple types are supported".to_owned()),
+            None => return error(item.pos(), "expected a type after `-`".to_owned())
+        };
+        if pending.is_empty() {
+            return error(item.pos(), "expected names before `-`".to_owned());
+        }
+        typed.extend(pending.drain(..).map(|(n, pos)| (n, type_name.clone(), pos)));
+    }
+    typed.extend(pending.into_iter().map(|(n, pos)| (n, "object".to_owned(), pos)));
+    Ok(typed)
+}
+
+/// A parsed PDDL domain, ready to be combined with problems.
+pub struct PddlDomain {
+    name: String,
+    types: Vec<String>,
+    predicates: Vec<(String, usize)>,
+    lifted: LiftedDomain
+}
+
+impl PddlDomain {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn lifted(&self) -> &LiftedDomain {
+        &self.lifted
+    }
+
+    fn check_type(&self, type_name: &str, pos: Pos) -> Result<(), PddlError> {
+        if type_name == "object" || self.types.iter().any(|t| t == type_name) {
+            Ok(())
+        } else {
+            error(pos, format!("undeclared type `{}`", type_name))
+        }
+    }
+
+    /// Reads an atomic formula `(pred arg ...)`, checking the predicate and that every
+    /// argument is accepted by `is_term`.
+    fn read_atom<F>(&self, sexp: &Sexp, is_term: &F) -> Result<String, PddlError>
+        where F: Fn(&str) -> bool {
+        let items = sexp.expect_list("an atom")?;
+        let pred = match items.first() {
+            Some(pred) => pred.expect_symbol("a predicate")?,
+            None => return error(sexp.pos(), "expected an atom, found `()`".to_owned())
+        };
+        match self.predicates.iter().find(|(p, _)| p == pred) {
+            Some(&(_, arity)) if arity == items.len() - 1 => {},
+            Some(&(_, arity)) => return error(sexp.pos(),
+                format!("predicate `{}` takes {} arguments, found {}", pred, arity, items.len() - 1)),
+            None => return error(items[0].pos(), format!("undeclared predicate `{}`", pred))
+        }
+        let mut args = Vec::with_capacity(items.len() - 1);
+        for item in &items[1..] {
+            let arg = item.expect_symbol("an argument")?;
+            if !is_term(arg) {
+                return error(item.pos(), format!("unknown argument `{}` to `{}`", arg, pred));
+            }
+            args.push(arg);
+        }
+        Ok(format_atom(pred, &args))
+    }
+
+    /// Flattens a conjunction of possibly negated atoms into `(atom, value)` pairs.
+    fn read_literals<F>(&self, sexp: &Sexp, is_term: &F, literals: &mut Vec<(String, bool)>)
+                        -> Result<(), PddlError>
+        where F: Fn(&str) -> bool {
+        let items = sexp.expect_list("a formula")?;
+        match sexp.head() {
+            None if items.is_empty() => {},
+            Some("and") => {
+                for item in &items[1..] {
+                    self.read_literals(item, is_term, literals)?;
+                }
+            },
+            Some("not") if items.len() == 2 => {
+                literals.push((self.read_atom(&items[1], is_term)?, false));
+            },
+            Some(head @ "or") | Some(head @ "imply") | Some(head @ "forall") | Some(head @ "exists") |
+            Some(head @ "when") | Some(head @ "=") | Some(head @ "not") =>
+                return error(sexp.pos(), format!("`{}` is not supported", head)),
+            _ => literals.push((self.read_atom(sexp, is_term)?, true))
+        }
+        Ok(())
+    }
+
+    fn read_action(&mut self, items: &[Sexp], pos: Pos) -> Result<(), PddlError> {
+        let name = match items.get(1) {
+            Some(name) => name.expect_symbol("an action name")?.to_owned(),
+            None => return error(pos, "expected an action name".to_owned())
+        };
+        if self.lifted.has_schema(&name) {
+            return error(items[1].pos(), format!("duplicate action `{}`", name));
+        }
+        self.lifted.set_cost(&name, 1);
+
+        let mut params: Vec<String> = Vec::new();
+        let mut pre = Vec::new();
+        let mut post = Vec::new();
+        let mut rest = items[2..].iter();
+        while let Some(key) = rest.next() {
+            let value = match rest.next() {
+                Some(value) => value,
+                None => return error(key.pos(), format!("expected a value after `{}`", key.expect_symbol("a keyword")?))
+            };
+            match key.expect_symbol("a keyword")? {
+                ":parameters" => {
+                    for (var, type_name, var_pos) in read_typed_list(value.expect_list("a parameter list")?)? {
+                        self.check_type(&type_name, var_pos)?;
+                        if !var.starts_with('?') || !self.lifted.add_param(&name, &var, &type_name) {
+                            return error(var_pos, format!("bad or duplicate parameter `{}`", var));
+                        }
+                        params.push(var);
+                    }
+                },
+                ":precondition" => {
+                    let lifted = &self.lifted;
+                    let is_term = |arg: &str| params.iter().any(|p| p == arg) || lifted.object_type(arg).is_some();
+                    self.read_literals(value, &is_term, &mut pre)?;
+                },
+                ":effect" => {
+                    let effects = match value.head() {
+                        Some("and") => &value.expect_list("an effect")?[1..],
+                        _ => ::std::slice::from_ref(value)
+                    };
+                    for effect in effects {
+                        if effect.head() == Some("increase") {
+                            let cost = self.read_cost(effect)?;
+                            self.lifted.set_cost(&name, cost);
+                            continue;
+                        }
+                        let lifted = &self.lifted;
+                        let is_term = |arg: &str| params.iter().any(|p| p == arg) || lifted.object_type(arg).is_some();
+                        self.read_literals(effect, &is_term, &mut post)?;
+                    }
+                },
+                other => return error(key.pos(), format!("unexpected `{}` in action `{}`", other, name))
+            }
+        }
+
+        for (atom, value) in pre {
+            self.lifted.set_pre(&name, &atom, value);
+        }
+        for (atom, value) in post {
+            self.lifted.set_post(&name, &atom, value);
+        }
+        Ok(())
+    }
+
+    /// Reads `(increase (total-cost) <n>)`.
+    fn read_cost(&self, sexp: &Sexp) -> Result<i32, PddlError> {
+        let items = sexp.expect_list("a cost")?;
+        let is_total_cost = items.len() == 3 && items[1].head() == Some("total-cost");
+        match items.get(2).and_then(Sexp::symbol).map(str::parse::<i32>) {
+            Some(Ok(cost)) if is_total_cost => Ok(cost),
+            _ => error(sexp.pos(), "expected `(increase (total-cost) <integer>)`".to_owned())
+        }
+    }
+}
+
+/// Parses a STRIPS `domain.pddl` with optional typing, negative preconditions and
+/// integer action costs.
+pub fn parse_domain(src: &str) -> Result<PddlDomain, PddlError> {
+    let root = read_sexp(src)?;
+    let (name, sections) = read_define(&root, "domain")?;
+    let mut domain = PddlDomain {
+        name: name.to_owned(),
+        types: Vec::new(),
+        predicates: Vec::new(),
+        lifted: LiftedDomain::new()
+    };
+
+    for section in sections {
+        let items = section.expect_list("a domain section")?;
+        match section.head() {
+            Some(":requirements") => {
+                for req in &items[1..] {
+                    let req_name = req.expect_symbol("a requirement")?;
+                    if !SUPPORTED_REQUIREMENTS.contains(&req_name) {
+                        return error(req.pos(), format!("requirement `{}` is not supported", req_name));
+                    }
+                }
+            },
+            Some(":types") => {
+                for (type_name, parent, _) in read_typed_list(&items[1..])? {
+                    domain.types.push(type_name.clone());
+                    if type_name != "object" {
+                        domain.lifted.add_type(&type_name, &parent);
+                    }
+                }
+            },
+            Some(":constants") => {
+                for (object, type_name, pos) in read_typed_list(&items[1..])? {
+                    domain.check_type(&type_name, pos)?;
+                    domain.lifted.add_object(&object, &type_name);
+                }
+            },
+            Some(":predicates") => {
+                for pred in &items[1..] {
+                    let parts = pred.expect_list("a predicate")?;
+                    let pred_name = match parts.first() {
+                        Some(p) => p.expect_symbol("a predicate name")?,
+                        None => return error(pred.pos(), "expected a predicate".to_owned())
+                    };
+                    let arity = read_typed_list(&parts[1..])?.len();
+                    domain.predicates.push((pred_name.to_owned(), arity));
+                }
+            },
+            Some(":functions") => {},
+            Some(":action") => domain.read_action(items, section.pos())?,
+            Some(other) => return error(section.pos(), format!("unsupported domain section `{}`", other)),
+            None => return error(section.pos(), "expected a domain section".to_owned())
+        }
+    }
+    Ok(domain)
+}
+
+/// Parses a `problem.pddl` for `domain`, grounds the domain over the problem's objects and
+/// returns the resulting planner with the initial state and goal.
+///
+/// The initial state is closed-world: every atom of the planner that is not listed in
+/// `:init` starts out false.
+pub fn parse_problem(domain: &PddlDomain, src: &str) -> Result<Problem, PddlError> {
+    let root = read_sexp(src)?;
+    let (_, sections) = read_define(&root, "problem")?;
+    let mut lifted = domain.lifted.clone();
+    let mut init = Vec::new();
+    let mut goal = Vec::new();
+    let mut goal_pos = None;
+    let mut objects_pos = root.pos();
+
+    for section in sections {
+        let items = section.expect_list("a problem section")?;
+        match section.head() {
+            Some(":domain") => {
+                let name = match items.get(1) {
+                    Some(name) => name.expect_symbol("a domain name")?,
+                    None => return error(section.pos(), "expected a domain name".to_owned())
+                };
+                if name != domain.name {
+                    return error(items[1].pos(), format!("problem is for domain `{}`, not `{}`", name, domain.name));
+                }
+            },
+            Some(":requirements") => {},
+            Some(":objects") => {
+                objects_pos = section.pos();
+                for (object, type_name, pos) in read_typed_list(&items[1..])? {
+                    domain.check_type(&type_name, pos)?;
+                    lifted.add_object(&object, &type_name);
+                }
+            },
+            Some(":init") => {
+                let is_object = |arg: &str| lifted.object_type(arg).is_some();
+                for fact in &items[1..] {
+                    // Numeric fluents such as `(= (total-cost) 0)` carry no atoms.
+                    if fact.head() != Some("=") {
+                        init.push(domain.read_atom(fact, &is_object)?);
+                    }
+                }
+            },
+            Some(":goal") => {
+                let is_object = |arg: &str| lifted.object_type(arg).is_some();
+                goal_pos = Some(section.pos());
+                match items.get(1) {
+                    Some(formula) if items.len() == 2 => domain.read_literals(formula, &is_object, &mut goal)?,
+                    _ => return error(section.pos(), "expected `(:goal <formula>)`".to_owned())
+                }
+            },
+            Some(":metric") => {
+                let minimizes_cost = items.len() == 3 && items[1].symbol() == Some("minimize")
+                    && items[2].head() == Some("total-cost");
+                if !minimizes_cost {
+                    return error(section.pos(), "only `(:metric minimize (total-cost))` is supported".to_owned());
+                }
+            },
+            Some(other) => return error(section.pos(), format!("unsupported problem section `{}`", other)),
+            None => return error(section.pos(), "expected a problem section".to_owned())
+        }
+    }
+
+    let goal_pos = match goal_pos {
+        Some(pos) => pos,
+        None => return error(root.pos(), "problem has no `:goal`".to_owned())
+    };
+    let ground_error = |pos: Pos, error| PddlError::Ground { line: pos.line, column: pos.column, error };
+    let mut builder = ActionPlanner::builder();
+    // Action schemas were checked while parsing the domain, so grounding can only run out of
+    // atoms, and the objects are what multiply them.
+    lifted.ground(&mut builder).map_err(|err| ground_error(objects_pos, err))?;
+    for (atom, _) in &goal {
+        if builder.register_atom(atom).is_none() {
+            return Err(ground_error(goal_pos, GroundError::TooManyAtoms));
+        }
+    }
+    let planner = builder.build();
+
+    let mut start = WorldState::new();
+    for atom in planner.atom_ids() {
+        start.set_id(atom, false);
+    }
+    // Facts that no action or goal mentions cannot affect the plan, so they are not atoms.
+    for fact in &init {
+        start.set(&planner, fact, true);
+    }
+    let mut goal_ws = WorldState::new();
+    for &(ref atom, value) in &goal {
+        goal_ws.set(&planner, atom, value);
+    }
+    Ok(Problem { planner, start, goal: goal_ws })
+}
+
+
+const RESERVED: [&str; 12] = ["and", "not", "or", "imply", "forall", "exists", "when", "either",
+                              "increase", "define", "object", "total-cost"];
+
+/// Maps atom and action names onto distinct PDDL identifiers.
+///
+/// Every atom becomes a nullary predicate and every action a parameterless action, so a name
+/// like `on(a, b)` is written as `on_a_b`.
+struct PddlNames {
+    atoms: Vec<String>,
+    actions: Vec<String>
+}
+
+impl PddlNames {
+    fn new(ap: &ActionPlanner) -> Self {
+        let mut used = Vec::new();
+        let atoms = ap.atom_names().iter().map(|n| unique_identifier(n, &mut used)).collect();
+        let actions = ap.actions().0.iter().map(|n| unique_identifier(n, &mut used)).collect();
+        PddlNames { atoms, actions }
+    }
+
+    fn literals(&self, ws: &WorldState) -> Vec<String> {
+        let mut literals = Vec::new();
+        for (idx, atom) in self.atoms.iter().enumerate() {
+            if !ws.dontcare.get(idx) {
+                literals.push(match ws.values.get(idx) {
+                    true => format!("({})", atom),
+                    false => format!("(not ({}))", atom)
+                });
+            }
+        }
+        literals
+    }
+}
+
+fn unique_identifier(name: &str, used: &mut Vec<String>) -> String {
+    let mut ident = String::with_capacity(name.len());
+    for c in name.chars().flat_map(char::to_lowercase) {
+        let c = match c.is_ascii_alphanumeric() || c == '-' {
+            true => c,
+            false => '_'
+        };
+        if !(c == '_' && ident.ends_with('_')) {
+            ident.push(c);
+        }
+    }
+    let trimmed_len = ident.trim_end_matches('_').len();
+    ident.truncate(trimmed_len);
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        ident.insert_str(0, "x_");
+    }
+    if RESERVED.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    let mut unique = ident.clone();
+    let mut suffix = 2;
+    while used.contains(&unique) {
+        unique = format!("{}-{}", ident, suffix);
+        suffix += 1;
+    }
+    used.push(unique.clone());
+    unique
+}
+
+fn conjunction(literals: &[String]) -> String {
+    match literals.len() {
+        1 => literals[0].clone(),
+        _ => format!("(and {})", literals.join(" "))
+    }
+}
+
+/// Writes `ap` as a grounded PDDL domain named `domain_name`.
+pub fn write_pddl_domain(ap: &ActionPlanner, domain_name: &str) -> String {
+    let names = PddlNames::new(ap);
+    let mut out = String::new();
+    out.push_str(&format!("(define (domain {})\n", domain_name));
+    out.push_str("  (:requirements :strips :negative-preconditions :action-costs)\n");
+    out.push_str("  (:predicates");
+    for atom in &names.atoms {
+        out.push_str(&format!(" ({})", atom));
+    }
+    out.push_str(")\n");
+    out.push_str("  (:functions (total-cost) - number)\n");
+
+    for (idx, (_, pre, cost, post)) in multizip(ap.actions()).enumerate() {
+        let mut effects = names.literals(post);
+        effects.push(format!("(increase (total-cost) {})", cost));
+        out.push_str(&format!("  (:action {}\n", names.actions[idx]));
+        out.push_str("    :parameters ()\n");
+        out.push_str(&format!("    :precondition (and {})\n", names.literals(pre).join(" ")));
+        out.push_str(&format!("    :effect {})\n", conjunction(&effects)));
+    }
+    out.push_str(")\n");
+    out
+}
+
+/// Writes a PDDL problem for the domain written by `write_pddl_domain`.
+///
+/// Atoms that are not set in `start` are false in the closed-world initial state.
+pub fn write_pddl_problem(ap: &ActionPlanner, domain_name: &str, problem_name: &str,
+                          start: &WorldState, goal: &WorldState) -> String {
+    let names = PddlNames::new(ap);
+    let mut out = String::new();
+    out.push_str(&format!("(define (problem {})\n", problem_name));
+    out.push_str(&format!("  (:domain {})\n", domain_name));
+    out.push_str("  (:init (= (total-cost) 0)");
+    for literal in names.literals(start) {
+        if !literal.starts_with("(not ") {
+            out.push_str(&format!(" {}", literal));
+        }
+    }
+    out.push_str(")\n");
+    out.push_str(&format!("  (:goal (and {}))\n", names.literals(goal).join(" ")));
+    out.push_str("  (:metric minimize (total-cost)))\n");
+    out
+}
+
+/// Writes `plan` in the IPC plan format, one `(action)` per line followed by its cost.
+pub fn write_pddl_plan(ap: &ActionPlanner, plan: &AStarPlan) -> String {
+    let names = PddlNames::new(ap);
+    let mut out = String::new();
+    // The first entry is the start state rather than an action.
+    for (name, _) in plan.iter().skip(1) {
+        match ap.lookup_action(name) {
+            Some(action) => out.push_str(&format!("({})\n", names.actions[action.index()])),
+            None => out.push_str(&format!("; unknown action {}\n", name))
+        }
+    }
+    out.push_str(&format!("; cost = {} (general cost)\n", plan.cost()));
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{parse_domain, parse_problem, PddlError, write_pddl_domain, write_pddl_problem, write_pddl_plan};
+    use ::goap::{ActionPlanner, WorldState};
+    use ::astar::AStar;
+    use ::test_domains::step_names;
+    use ::lifted::GroundError;
+
+    const BLOCKS: &str = "
+        ; Four-operator blocks world.
+        (define (domain BLOCKS)
+          (:requirements :strips :typing)
+          (:types block)
+          (:predicates (on ?x - block ?y - block) (ontable ?x - block) (clear ?x - block)
+                       (handempty) (holding ?x - block))
+          (:action pick-up
+             :parameters (?x - block)
+             :precondition (and (clear ?x) (ontable ?x) (handempty))
+             :effect (and (not (ontable ?x)) (not (clear ?x)) (not (handempty)) (holding ?x)))
+          (:action put-down
+             :parameters (?x - block)
+             :precondition (holding ?x)
+             :effect (and (not (holding ?x)) (clear ?x) (handempty) (ontable ?x)))
+          (:action stack
+             :parameters (?x - block ?y - block)
+             :precondition (and (holding ?x) (clear ?y))
+             :effect (and (not (holding ?x)) (not (clear ?y)) (clear ?x) (handempty) (on ?x ?y)))
+          (:action unstack
+             :parameters (?x - block ?y - block)
+             :precondition (and (on ?x ?y) (clear ?x) (handempty))
+             :effect (and (holding ?x) (clear ?y) (not (clear ?x)) (not (handempty)) (not (on ?x ?y)))))";
+
+    const SUSSMAN: &str = "
+        (define (problem sussman)
+          (:domain blocks)
+          (:objects a b c - block)
+          (:init (clear c) (clear b) (ontable a) (ontable b) (on c a) (handempty))
+          (:goal (and (on a b) (on b c))))";
+
+    #[test]
+    fn solves_sussman_anomaly() {
+        let domain = parse_domain(BLOCKS).unwrap();
+        assert_eq!("blocks", domain.name());
+        let problem = parse_problem(&domain, SUSSMAN).unwrap();
+        assert_eq!(6 + 2 * 9, problem.planner.action_ids().count());
+
+        let plan = AStar::new().plan(&problem.planner, &problem.start, &problem.goal).unwrap();
+        assert_eq!(vec!["unstack(c, a)", "put-down(c)", "pick-up(b)", "stack(b, c)", "pick-up(a)", "stack(a, b)"],
+                   step_names(&plan));
+    }
+
+    #[test]
+    fn reads_action_costs() {
+        let domain = parse_domain("
+            (define (domain lights)
+              (:requirements :strips :action-costs)
+              (:predicates (on))
+              (:functions (total-cost))
+              (:action flip :parameters () :precondition (not (on))
+                 :effect (and (on) (increase (total-cost) 5))))").unwrap();
+        let problem = parse_problem(&domain, "
+            (define (problem p) (:domain lights) (:init (= (total-cost) 0)) (:goal (on))
+              (:metric minimize (total-cost)))").unwrap();
+        let plan = AStar::new().plan(&problem.planner, &problem.start, &problem.goal).unwrap();
+        assert_eq!(5, plan.cost());
+    }
+
+    #[test]
+    fn grounds_aliased_parameters() {
+        let domain = parse_domain("
+            (define (domain alias)
+              (:requirements :strips :negative-preconditions)
+              (:predicates (p ?x) (q ?x))
+              (:action a :parameters (?x ?y) :precondition (and (not (p ?y)) (p ?x)) :effect (q ?x))
+              (:action b :parameters (?x ?y) :effect (and (p ?x) (not (p ?y)))))").unwrap();
+        let problem = parse_problem(&domain, "
+            (define (problem p) (:domain alias) (:objects o) (:init) (:goal (q o)))").unwrap();
+        let ap = &problem.planner;
+
+        // `a(o, o)` needs `p(o)` both ways, so it is left out; `b(o, o)` adds what it deletes.
+        assert_eq!(None, ap.lookup_action("a(o, o)"));
+        let b = ap.lookup_action("b(o, o)").unwrap();
+        assert_eq!(Some(true), ap.apply(b, &problem.start).get(ap.lookup_atom("p(o)").unwrap()));
+        assert!(AStar::new().plan(ap, &problem.start, &problem.goal).is_none());
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let err = parse_domain("(define (domain d)\n  (:predicates (p))\n  (:action a :effect (q)))").err();
+        assert_eq!(Some(PddlError::Parse { line: 3, column: 23, message: "undeclared predicate `q`".to_owned() }),
+                   err);
+
+        let err = parse_domain("(define (domain d)\n  (:requirements :adl))").err();
+        assert_eq!(Some(PddlError::Parse { line: 2, column: 18, message: "requirement `:adl` is not supported".to_owned() }),
+                   err);
+
+        let err = parse_domain("(define (domain d)").err();
+        assert_eq!(Some(PddlError::Parse { line: 1, column: 1, message: "unclosed `(`".to_owned() }), err);
+
+        let domain = parse_domain("(define (domain d)\n  (:predicates (on ?x ?y))\n  (:action a :parameters (?x) :effect (on ?x ?z)))").err();
+        assert_eq!(Some(PddlError::Parse { line: 3, column: 46, message: "unknown argument `?z` to `on`".to_owned() }),
+                   domain);
+    }
+
+    #[test]
+    fn reports_problem_error_positions() {
+        let domain = parse_domain("(define (domain d) (:predicates (lit ?x)) (:action flip :parameters (?x) :effect (lit ?x)))")
+            .unwrap();
+        let err = parse_problem(&domain, "(define (problem p) (:domain d)\n  (:objects a) (:init))").err();
+        assert_eq!(Some(PddlError::Parse { line: 1, column: 1, message: "problem has no `:goal`".to_owned() }), err);
+
+        let objects: Vec<String> = (0..65).map(|i| format!("o{}", i)).collect();
+        let src = format!("(define (problem p) (:domain d)\n  (:objects {}) (:goal (and)))", objects.join(" "));
+        assert_eq!(Some(PddlError::Ground { line: 2, column: 3, error: GroundError::TooManyAtoms }),
+                   parse_problem(&domain, &src).err());
+    }
+
+    #[test]
+    fn exports_round_trip() {
+        let mut builder = ActionPlanner::builder();
+        builder.set_pre("aim", "enemyvisible", true);
+        builder.set_pre("aim", "weaponloaded", true);
+        builder.set_post("aim", "enemylinedup", true);
+        builder.set_pre("shoot", "enemylinedup", true);
+        builder.set_post("shoot", "enemyalive", false);
+        builder.set_cost("shoot", 3);
+        builder.set_pre("load", "armedwithgun", true);
+        builder.set_post("load", "weaponloaded", true);
+        builder.set_pre("or", "enemyalive", false);
+        builder.set_post("or", "enemyvisible", false);
+        let ap = builder.build();
+
+        let mut start = WorldState::new();
+        start.set(&ap, "enemyvisible", true);
+        start.set(&ap, "armedwithgun", true);
+        start.set(&ap, "weaponloaded", false);
+        start.set(&ap, "enemyalive", true);
+        let mut goal = WorldState::new();
+        goal.set(&ap, "enemyalive", false);
+
+        let domain_src = write_pddl_domain(&ap, "combat");
+        let problem_src = write_pddl_problem(&ap, "combat", "ambush", &start, &goal);
+        let domain = parse_domain(&domain_src).unwrap();
+        let problem = parse_problem(&domain, &problem_src).unwrap();
+        assert!(problem.planner.lookup_action("or_").is_some());
+
+        let plan = AStar::new().plan(&ap, &start, &goal).unwrap();
+        let reparsed = AStar::new().plan(&problem.planner, &problem.start, &problem.goal).unwrap();
+        assert_eq!(plan.cost(), reparsed.cost());
+        assert_eq!("(load)\n(aim)\n(shoot)\n; cost = 5 (general cost)\n", write_pddl_plan(&ap, &plan));
+        assert_eq!(write_pddl_plan(&ap, &plan), write_pddl_plan(&problem.planner, &reparsed));
+    }
+}
@@ -0,0 +1,161 @@
+use ::goap::{WorldState, ActionPlanner, AtomId, ActionId};
+use ::astar::{AStar, AStarPlan};
+use ::bitset::BitSetU64;
+
+/// The result of `prune`: a smaller planner for one start and goal, and what was left out.
+#[derive(Debug)]
+pub struct Pruning {
+    /// The kept actions over all of the original atoms, so states and `AtomId`s built for the
+    /// original planner work unchanged. Its `ActionId`s are not those of the original.
+    pub planner: ActionPlanner,
+    /// Atoms the goal depends on, directly or through the preconditions of relevant actions.
+    pub relevant_atoms: Vec<AtomId>,
+    /// Original actions that set no relevant atom.
+    pub irrelevant: Vec<ActionId>,
+    /// Original actions that are relevant but whose preconditions can never be met from the
+    /// start.
+    pub unreachable: Vec<ActionId>
+}
+
+/// Finds the actions that can ever apply from `start`, returned by index, along with the
+/// atoms that can ever be true and those that can ever be false.
+///
+/// Delete effects are ignored and each atom is only tracked as possibly true and possibly
+/// false, so this over-approximates: an action it calls unreachable truly is. Atoms `start`
+/// leaves as don't-care are taken to be possibly either.
+pub(crate) fn reachability(ap: &ActionPlanner, start: &WorldState) -> (Vec<bool>, BitSetU64, BitSetU64) {
+    let unknown = start.dontcare;
+    let mut can_be_true = (start.values & start.cares()) | unknown;
+    let mut can_be_false = ((start.values ^ BitSetU64::full()) & start.cares()) | unknown;
+    let mut reachable = vec![false; ap.action_ids().count()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for action in ap.action_ids() {
+            let (pre, post) = (ap.pre(action), ap.post(action));
+            let needs_true = pre.values & pre.cares();
+            let needs_false = (pre.values ^ BitSetU64::full()) & pre.cares();
+            if !reachable[action.index()] && (needs_true & can_be_true) == needs_true && (needs_false & can_be_false) == needs_false {
+                reachable[action.index()] = true;
+                can_be_true |= post.values & post.cares();
+                can_be_false |= (post.values ^ BitSetU64::full()) & post.cares();
+                changed = true;
+            }
+        }
+    }
+    (reachable, can_be_true, can_be_false)
+}
+
+/// Works backwards from `goal` to the atoms and actions that can matter to it, and forwards
+/// from `start` to the actions that can ever apply, then keeps only actions that are both.
+///
+/// Reachability is that of `reachability`, so it never prunes an action some plan needs.
+pub fn prune(ap: &ActionPlanner, start: &WorldState, goal: &WorldState) -> Pruning {
+    let mut relevant = goal.cares();
+    let mut relevant_actions = vec![false; ap.action_ids().count()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for action in ap.action_ids() {
+            if !relevant_actions[action.index()] && (ap.post(action).cares() & relevant) != BitSetU64::empty() {
+                relevant_actions[action.index()] = true;
+                relevant |= ap.pre(action).cares();
+                changed = true;
+            }
+        }
+    }
+
+    let (reachable, _, _) = reachability(ap, start);
+
+    let mut builder = ActionPlanner::builder();
+    for name in ap.atom_names() {
+        builder.register_atom(name);
+    }
+    let mut pruning = Pruning {
+        planner: ActionPlanner::default(),
+        relevant_atoms: ap.atom_ids().filter(|atom| relevant.get(atom.index())).collect(),
+        irrelevant: Vec::new(),
+        unreachable: Vec::new()
+    };
+    for action in ap.action_ids() {
+        if !relevant_actions[action.index()] {
+            pruning.irrelevant.push(action);
+        } else if !reachable[action.index()] {
+            pruning.unreachable.push(action);
+        } else {
+            let kept = builder.register_action(ap.name(action)).unwrap();
+            builder.set_cost_id(kept, ap.cost(action));
+            let (pre, post) = (ap.pre(action), ap.post(action));
+            for atom in ap.atom_ids() {
+                if !pre.dontcare.get(atom.index()) {
+                    builder.set_pre_id(kept, atom, pre.values.get(atom.index()));
+                }
+                if !post.dontcare.get(atom.index()) {
+                    builder.set_post_id(kept, atom, post.values.get(atom.index()));
+                }
+            }
+        }
+    }
+    pruning.planner = builder.build();
+    pruning
+}
+
+impl AStar {
+    /// Prunes `ap` for this start and goal with `prune`, then plans on what is left.
+    pub fn plan_pruned(&mut self, ap: &ActionPlanner, start: &WorldState, goal: &WorldState)
+                       -> (Option<AStarPlan>, Pruning) {
+        let pruning = prune(ap, start, goal);
+        (self.plan(&pruning.planner, start, goal), pruning)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::prune;
+    use ::goap::{ActionPlanner, ActionId, WorldState};
+    use ::astar::AStar;
+
+    #[test]
+    fn prunes_irrelevant_and_unreachable_actions() {
+        let mut builder = ActionPlanner::builder();
+        builder.action("load").requires("armedwithgun").sets("weaponloaded").build().unwrap();
+        builder.action("shoot").requires("weaponloaded").clears("enemyalive").build().unwrap();
+        builder.action("detonatebomb").requires("armedwithbomb").clears("enemyalive").build().unwrap();
+        builder.action("dance").sets("happy").build().unwrap();
+        builder.action("sing").requires("happy").sets("tired").build().unwrap();
+        let ap = builder.build();
+        let start = WorldState::builder(&ap)
+            .holds("armedwithgun").lacks("weaponloaded").holds("enemyalive").lacks("armedwithbomb")
+            .lacks("happy").lacks("tired")
+            .build().unwrap();
+        let goal = WorldState::builder(&ap).lacks("enemyalive").build().unwrap();
+
+        let pruning = prune(&ap, &start, &goal);
+        let names = |actions: &[ActionId]| actions.iter().map(|&a| ap.name(a).to_string()).collect::<Vec<_>>();
+        assert_eq!(vec!["dance", "sing"], names(&pruning.irrelevant));
+        assert_eq!(vec!["detonatebomb"], names(&pruning.unreachable));
+        assert_eq!(2, pruning.planner.action_ids().count());
+        assert_eq!(ap.atom_names(), pruning.planner.atom_names());
+        let relevant: Vec<&str> = pruning.relevant_atoms.iter().map(|&a| &**ap.atom_name(a)).collect();
+        assert_eq!(vec!["armedwithgun", "weaponloaded", "enemyalive", "armedwithbomb"], relevant);
+
+        let mut astar = AStar::new();
+        let full = astar.plan(&ap, &start, &goal).unwrap();
+        let (pruned, _) = astar.plan_pruned(&ap, &start, &goal);
+        let pruned = pruned.unwrap();
+        assert_eq!(full.cost(), pruned.cost());
+        assert!(full.iter().eq(pruned.iter()));
+    }
+
+    #[test]
+    fn unknown_start_atoms_stay_reachable() {
+        let mut builder = ActionPlanner::builder();
+        builder.action("detonatebomb").requires("armedwithbomb").clears("enemyalive").build().unwrap();
+        let ap = builder.build();
+        let start = WorldState::builder(&ap).holds("enemyalive").build().unwrap();
+        let goal = WorldState::builder(&ap).lacks("enemyalive").build().unwrap();
+        assert!(prune(&ap, &start, &goal).unreachable.is_empty());
+    }
+}
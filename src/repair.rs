@@ -0,0 +1,151 @@
+use ::goap::{WorldState, ActionPlanner, ActionId};
+use ::astar::{AStar, AStarPlan};
+use ::replay::simulate;
+
+/// How `AStar::repair` produced its plan.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Repair {
+    /// The rest of the old plan still reaches the goal once its first `skipped` steps, whose
+    /// effects already hold, are dropped. `skipped` may be zero.
+    Skipped { skipped: usize },
+    /// The rest of the old plan works with `inserted` new steps placed before its step `at`
+    /// (counting from 1 at the first remaining step) to re-establish that step's
+    /// preconditions, or to finish off the goal when `at` is past the end.
+    Patched { at: usize, inserted: usize },
+    /// The old plan could not be saved and a new one was searched from scratch.
+    Replanned
+}
+
+fn make_plan(ap: &ActionPlanner, state: &WorldState, actions: &[ActionId]) -> AStarPlan {
+    let mut plan = AStarPlan::new();
+    plan.entries.push_back(("root".into(), *state));
+    let mut ws = *state;
+    for &action in actions {
+        ws = ap.apply(action, &ws);
+        plan.entries.push_back((ap.name(action).clone(), ws));
+        plan.cost += ap.cost(action);
+    }
+    plan
+}
+
+impl AStar {
+    /// Mends `plan` after the world turned out to be in `state` at its entry `from`, rather
+    /// than repairing by a full search.
+    ///
+    /// First tries to drop leading remaining steps that are no longer needed. Failing that it
+    /// replays the remaining steps up to the first one that cannot apply, or to the end if
+    /// the goal is then still unmet, and searches a sub-plan from there to that step's
+    /// preconditions or to the goal. If the patched plan still does not reach the goal, or
+    /// the plan names actions `ap` lacks, it plans again from scratch. Returns `None` only
+    /// when no plan reaches the goal from `state`.
+    pub fn repair(&mut self, ap: &ActionPlanner, plan: &AStarPlan, from: usize, state: &WorldState,
+                  goal: &WorldState) -> Option<(AStarPlan, Repair)> {
+        let remaining: Option<Vec<ActionId>> = plan.iter().skip(from + 1)
+            .map(|(name, _)| ap.lookup_action(name))
+            .collect();
+        let reaches = |actions: &[ActionId]| simulate(ap, state, actions)
+            .is_ok_and(|states| states[states.len() - 1].satisfies(goal));
+        if let Some(remaining) = remaining {
+            for skipped in (0..=remaining.len()).rev() {
+                let rest = &remaining[skipped..];
+                if reaches(rest) {
+                    return Some((make_plan(ap, state, rest), Repair::Skipped { skipped }));
+                }
+            }
+
+            let (at, states) = match simulate(ap, state, &remaining) {
+                Ok(states) => (remaining.len(), states),
+                Err(failed) => failed
+            };
+            let stuck = states[states.len() - 1];
+            let target = remaining.get(at).map_or(goal, |&action| ap.pre(action));
+            if let Some(patch) = self.plan(ap, &stuck, target) {
+                let patch: Vec<ActionId> = patch.iter().skip(1).filter_map(|(name, _)| ap.lookup_action(name)).collect();
+                let actions = [&remaining[..at], &patch[..], &remaining[at..]].concat();
+                if reaches(&actions) {
+                    return Some((make_plan(ap, state, &actions), Repair::Patched { at: at + 1, inserted: patch.len() }));
+                }
+            }
+        }
+        self.plan(ap, state, goal).map(|plan| (plan, Repair::Replanned))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::Repair;
+    use ::goap::ActionPlanner;
+    use ::astar::AStar;
+    use ::replay::validate_plan;
+    use ::test_domains::{ambush, ambush_start, enemy_dead, step_names};
+
+    fn planner() -> ActionPlanner {
+        let mut builder = ActionPlanner::builder();
+        ambush(&mut builder);
+        builder.action("pickup").sets("armedwithgun").build().unwrap();
+        builder.action("detonatebomb").cost(10).requires("enemyvisible").clears("enemyalive").build().unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn skips_steps_that_already_hold() {
+        let ap = planner();
+        let start = ambush_start(&ap);
+        let goal = enemy_dead(&ap);
+        let mut astar = AStar::new();
+        let plan = astar.plan(&ap, &start, &goal).unwrap();
+        assert_eq!(4, plan.iter().count() - 1);
+
+        // The enemy walked into the crosshairs before anything ran.
+        let mut state = start;
+        state.set(&ap, "enemylinedup", true);
+        let (repaired, how) = astar.repair(&ap, &plan, 0, &state, &goal).unwrap();
+        assert_eq!(Repair::Skipped { skipped: 3 }, how);
+        assert_eq!(vec!["shoot"], step_names(&repaired));
+        assert_eq!(Ok(()), validate_plan(&ap, &state, &goal, &repaired));
+
+        let (repaired, how) = astar.repair(&ap, &plan, 0, &start, &goal).unwrap();
+        assert_eq!(Repair::Skipped { skipped: 0 }, how);
+        assert_eq!(step_names(&plan), step_names(&repaired));
+    }
+
+    #[test]
+    fn patches_lost_preconditions() {
+        let ap = planner();
+        let start = ambush_start(&ap);
+        let goal = enemy_dead(&ap);
+        let mut astar = AStar::new();
+        let plan = astar.plan(&ap, &start, &goal).unwrap();
+
+        // The gun was dropped after the first step.
+        let mut state = plan.iter().nth(1).unwrap().1;
+        state.set(&ap, "armedwithgun", false);
+        let (repaired, how) = astar.repair(&ap, &plan, 1, &state, &goal).unwrap();
+        assert_eq!(Repair::Patched { at: 1, inserted: 1 }, how);
+        assert_eq!(Some(&"pickup"), step_names(&repaired).first());
+        assert_eq!(Ok(()), validate_plan(&ap, &state, &goal, &repaired));
+    }
+
+    #[test]
+    fn falls_back_to_full_search() {
+        let ap = planner();
+        let start = ambush_start(&ap);
+        let goal = enemy_dead(&ap);
+        let mut astar = AStar::new();
+        let plan = astar.plan(&ap, &start, &goal).unwrap();
+
+        // A planner with the same atoms but none of the plan's actions.
+        let mut other = ActionPlanner::builder();
+        for name in ap.atom_names() {
+            other.register_atom(name);
+        }
+        other.action("detonatebomb").requires("enemyvisible").clears("enemyalive").build().unwrap();
+        other.action("lookaround").sets("enemyvisible").build().unwrap();
+        let other = other.build();
+        let (repaired, how) = astar.repair(&other, &plan, 0, &start, &goal).unwrap();
+        assert_eq!(Repair::Replanned, how);
+        assert_eq!(vec!["lookaround", "detonatebomb"], step_names(&repaired));
+    }
+}
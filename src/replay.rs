@@ -0,0 +1,186 @@
+use ::goap::{WorldState, ActionPlanner, ActionId};
+use ::astar::AStarPlan;
+use ::bitset::BitSetU64;
+use std::error::Error;
+use std::fmt;
+
+/// Why `validate_plan` rejected a plan. Steps are numbered from 1, with the start entry
+/// being step 0.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PlanValidationError {
+    /// The plan does not open with a `root` entry holding the start state.
+    BadStart,
+    /// A step names an action the planner does not have.
+    UnknownAction { step: usize, action: String },
+    /// A step's action needs `atom` to hold a value the state before it does not have.
+    PreconditionFailed { step: usize, action: String, atom: String },
+    /// The state recorded for a step is not the one its action produces.
+    StateMismatch { step: usize, action: String },
+    /// The final state gets these goal atoms wrong.
+    GoalNotMet { missing: Vec<String> },
+    /// The plan's recorded cost is not the sum of its action costs.
+    CostMismatch { recorded: i32, actual: i32 }
+}
+
+impl fmt::Display for PlanValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PlanValidationError::BadStart => write!(f, "plan does not begin at the start state"),
+            PlanValidationError::UnknownAction { step, ref action } =>
+                write!(f, "step {}: unknown action `{}`", step, action),
+            PlanValidationError::PreconditionFailed { step, ref action, ref atom } =>
+                write!(f, "step {}: `{}` cannot apply, precondition `{}` does not hold", step, action, atom),
+            PlanValidationError::StateMismatch { step, ref action } =>
+                write!(f, "step {}: state after `{}` differs from the recorded one", step, action),
+            PlanValidationError::GoalNotMet { ref missing } =>
+                write!(f, "plan ends without reaching the goal; missing {}", missing.join(", ")),
+            PlanValidationError::CostMismatch { recorded, actual } =>
+                write!(f, "plan records cost {} but its actions cost {}", recorded, actual)
+        }
+    }
+}
+
+impl Error for PlanValidationError {}
+
+/// The states `actions` pass through from `start`, or the index of the first action whose
+/// preconditions fail along with the states up to it.
+pub(crate) fn simulate(ap: &ActionPlanner, start: &WorldState, actions: &[ActionId])
+                       -> Result<Vec<WorldState>, (usize, Vec<WorldState>)> {
+    let mut states = vec![*start];
+    for (i, &action) in actions.iter().enumerate() {
+        let last = states[states.len() - 1];
+        if !ap.is_applicable(action, &last) {
+            return Err((i, states));
+        }
+        states.push(ap.apply(action, &last));
+    }
+    Ok(states)
+}
+
+/// Replays `plan` from `start` under `ap`, checking that every action exists and can apply,
+/// that each recorded state is the one its action produces, that the last state meets
+/// `goal`, and that the recorded cost is the sum of the action costs.
+pub fn validate_plan(ap: &ActionPlanner, start: &WorldState, goal: &WorldState, plan: &AStarPlan)
+                     -> Result<(), PlanValidationError> {
+    let mut steps = plan.iter();
+    match steps.next() {
+        Some((name, ws)) if &**name == "root" && ws.diff(start).next().is_none() => {},
+        _ => return Err(PlanValidationError::BadStart)
+    }
+
+    // Simulate up to the first unknown action, then report problems in step order.
+    let steps: Vec<_> = steps.collect();
+    let actions: Vec<ActionId> = steps.iter().map_while(|(name, _)| ap.lookup_action(name)).collect();
+    let (states, failed) = match simulate(ap, start, &actions) {
+        Ok(states) => (states, None),
+        Err((i, states)) => (states, Some(i))
+    };
+    for (i, (name, recorded)) in steps.iter().enumerate().take(states.len() - 1) {
+        if states[i + 1].diff(recorded).next().is_some() {
+            return Err(PlanValidationError::StateMismatch { step: i + 1, action: name.to_string() });
+        }
+    }
+    let state = states[states.len() - 1];
+    if let Some(i) = failed {
+        let pre = ap.pre(actions[i]);
+        let wrong = (state.values ^ pre.values) & pre.cares();
+        let atom = ap.atom_ids().find(|atom| wrong.get(atom.index())).unwrap();
+        return Err(PlanValidationError::PreconditionFailed {
+            step: i + 1,
+            action: steps[i].0.to_string(),
+            atom: ap.atom_name(atom).to_string()
+        });
+    }
+    if let Some((name, _)) = steps.get(actions.len()) {
+        return Err(PlanValidationError::UnknownAction { step: actions.len() + 1, action: name.to_string() });
+    }
+
+    let wrong = (state.values ^ goal.values) & goal.cares();
+    if wrong != BitSetU64::empty() {
+        let missing = ap.atom_ids()
+            .filter(|atom| wrong.get(atom.index()))
+            .map(|atom| ap.atom_name(atom).to_string())
+            .collect();
+        return Err(PlanValidationError::GoalNotMet { missing });
+    }
+    let cost = actions.iter().map(|&action| ap.cost(action)).sum();
+    if cost != plan.cost() {
+        return Err(PlanValidationError::CostMismatch { recorded: plan.cost(), actual: cost });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{validate_plan, PlanValidationError};
+    use ::goap::{ActionPlanner, WorldState};
+    use ::astar::{AStar, Heuristic};
+    use ::test_domains::{ambush, ambush_start, enemy_dead};
+    use std::sync::Arc;
+
+    fn planner() -> ActionPlanner {
+        let mut builder = ActionPlanner::builder();
+        ambush(&mut builder);
+        builder.action("approach").requires("enemyvisible").sets("nearenemy").build().unwrap();
+        builder.action("detonatebomb").cost(3).requires("armedwithbomb").requires("nearenemy")
+            .clears("alive").clears("enemyalive").build().unwrap();
+        builder.action("flee").requires("enemyvisible").clears("nearenemy").build().unwrap();
+        builder.action("pickup").sets("armedwithgun").build().unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn every_planner_produces_valid_plans() {
+        let ap = planner();
+        let goal = enemy_dead(&ap);
+        let mut astar = AStar::new();
+        for bits in 0..64u32 {
+            let mut start = WorldState::new();
+            for (i, name) in ["armedwithgun", "enemyvisible", "weaponloaded", "armedwithbomb", "nearenemy", "alive"]
+                    .iter().enumerate() {
+                start.set(&ap, name, bits & (1 << i) != 0);
+            }
+            start.set(&ap, "enemyalive", true);
+            for &(heuristic, greedy) in &[(Heuristic::Mismatch, false), (Heuristic::Zero, false), (Heuristic::Mismatch, true)] {
+                astar.set_heuristic(heuristic);
+                astar.set_greedy(greedy);
+                let plan = astar.plan(&ap, &start, &goal).unwrap();
+                assert_eq!(Ok(()), validate_plan(&ap, &start, &goal, &plan));
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_broken_plans() {
+        let ap = planner();
+        let start = ambush_start(&ap);
+        let goal = enemy_dead(&ap);
+        let plan = AStar::new().plan(&ap, &start, &goal).unwrap();
+
+        let mut costly = AStar::new().plan(&ap, &start, &goal).unwrap();
+        costly.cost += 1;
+        assert_eq!(Err(PlanValidationError::CostMismatch { recorded: plan.cost() + 1, actual: plan.cost() }),
+                   validate_plan(&ap, &start, &goal, &costly));
+
+        let mut renamed = AStar::new().plan(&ap, &start, &goal).unwrap();
+        renamed.entries[1].0 = Arc::from("teleport");
+        assert_eq!(Err(PlanValidationError::UnknownAction { step: 1, action: "teleport".to_owned() }),
+                   validate_plan(&ap, &start, &goal, &renamed));
+
+        let mut short = AStar::new().plan(&ap, &start, &goal).unwrap();
+        short.entries.pop_back();
+        assert_eq!(Err(PlanValidationError::GoalNotMet { missing: vec!["enemyalive".to_owned()] }),
+                   validate_plan(&ap, &start, &goal, &short));
+
+        let unarmed = WorldState::builder(&ap).lacks("armedwithgun").holds("enemyalive").build().unwrap();
+        let err = validate_plan(&ap, &unarmed, &goal, &plan).unwrap_err();
+        assert_eq!(PlanValidationError::BadStart, err);
+
+        // Dropping scouting and loading leaves aiming first, before its preconditions hold.
+        let mut skipped = AStar::new().plan(&ap, &start, &goal).unwrap();
+        skipped.entries.drain(1..3);
+        assert!(matches!(validate_plan(&ap, &start, &goal, &skipped),
+                         Err(PlanValidationError::PreconditionFailed { step: 1, .. })));
+    }
+}
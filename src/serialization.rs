@@ -0,0 +1,346 @@
+use ::goap::{WorldState, ActionPlanner, Problem};
+use ::astar::AStarPlan;
+use ::bitset::BitSetU64;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{self, DeserializeSeed, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+impl Serialize for BitSetU64 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.bits())
+    }
+}
+
+impl<'de> Deserialize<'de> for BitSetU64 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u64::deserialize(deserializer).map(BitSetU64::new_with)
+    }
+}
+
+/// Serialises a `WorldState` or `AStarPlan` with its atoms written by name, e.g.
+/// `{"enemyvisible": true, "weaponloaded": false}` for a state. Atoms left as don't-care are
+/// omitted.
+pub struct Named<'a, T: 'a> {
+    ap: &'a ActionPlanner,
+    value: &'a T
+}
+
+impl<'a, T> Named<'a, T> {
+    pub fn new(ap: &'a ActionPlanner, value: &'a T) -> Self {
+        Named { ap, value }
+    }
+}
+
+impl<'a> Serialize for Named<'a, WorldState> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let ws = self.value;
+        let atoms = self.ap.atom_ids().filter(|atom| !ws.dontcare.get(atom.index()));
+        let mut map = serializer.serialize_map(None)?;
+        for atom in atoms {
+            map.serialize_entry(&**self.ap.atom_name(atom), &ws.values.get(atom.index()))?;
+        }
+        map.end()
+    }
+}
+
+#[derive(Serialize)]
+struct StepSer<'a> {
+    action: &'a str,
+    state: Named<'a, WorldState>
+}
+
+#[derive(Serialize)]
+struct PlanSer<'a> {
+    cost: i32,
+    steps: Vec<StepSer<'a>>
+}
+
+impl<'a> Serialize for Named<'a, AStarPlan> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PlanSer {
+            cost: self.value.cost(),
+            steps: self.value.iter()
+                .map(|(action, ws)| StepSer { action, state: Named::new(self.ap, ws) })
+                .collect()
+        }.serialize(serializer)
+    }
+}
+
+/// Deserialises a `WorldState` or `AStarPlan` written by `Named`, resolving atom names
+/// against `ap`. Names `ap` does not know are an error.
+pub struct NamedSeed<'a, T> {
+    ap: &'a ActionPlanner,
+    marker: PhantomData<T>
+}
+
+impl<'a, T> NamedSeed<'a, T> {
+    pub fn new(ap: &'a ActionPlanner) -> Self {
+        NamedSeed { ap, marker: PhantomData }
+    }
+}
+
+/// Atom values in the order they were written.
+#[derive(Default)]
+struct AtomValues(Vec<(String, bool)>);
+
+impl<'de> Deserialize<'de> for AtomValues {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AtomValuesVisitor;
+
+        impl<'de> Visitor<'de> for AtomValuesVisitor {
+            type Value = AtomValues;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a map from atom names to booleans")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<AtomValues, M::Error> {
+                let mut values = Vec::new();
+                while let Some(entry) = access.next_entry()? {
+                    values.push(entry);
+                }
+                Ok(AtomValues(values))
+            }
+        }
+
+        deserializer.deserialize_map(AtomValuesVisitor)
+    }
+}
+
+impl AtomValues {
+    fn resolve<E: de::Error>(&self, ap: &ActionPlanner) -> Result<WorldState, E> {
+        let mut ws = WorldState::new();
+        for &(ref name, value) in &self.0 {
+            if !ws.set(ap, name, value) {
+                return Err(E::custom(format!("unknown atom `{}`", name)));
+            }
+        }
+        Ok(ws)
+    }
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for NamedSeed<'a, WorldState> {
+    type Value = WorldState;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<WorldState, D::Error> {
+        AtomValues::deserialize(deserializer)?.resolve(self.ap)
+    }
+}
+
+#[derive(Deserialize)]
+struct StepDe {
+    action: String,
+    state: AtomValues
+}
+
+#[derive(Deserialize)]
+struct PlanDe {
+    cost: i32,
+    steps: Vec<StepDe>
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for NamedSeed<'a, AStarPlan> {
+    type Value = AStarPlan;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<AStarPlan, D::Error> {
+        let repr = PlanDe::deserialize(deserializer)?;
+        let mut plan = AStarPlan::new();
+        plan.cost = repr.cost;
+        for step in repr.steps {
+            let ws = step.state.resolve(self.ap)?;
+            plan.entries.push_back((Arc::from(step.action), ws));
+        }
+        Ok(plan)
+    }
+}
+
+#[derive(Serialize)]
+struct ActionSer<'a> {
+    name: &'a str,
+    cost: i32,
+    pre: Named<'a, WorldState>,
+    post: Named<'a, WorldState>
+}
+
+#[derive(Serialize)]
+struct PlannerSer<'a> {
+    atoms: Vec<&'a str>,
+    actions: Vec<ActionSer<'a>>
+}
+
+impl<'a> PlannerSer<'a> {
+    fn new(ap: &'a ActionPlanner) -> Self {
+        PlannerSer {
+            atoms: ap.atom_names().iter().map(|n| &**n).collect(),
+            actions: ap.action_ids()
+                .map(|action| ActionSer {
+                    name: ap.name(action),
+                    cost: ap.cost(action),
+                    pre: Named::new(ap, ap.pre(action)),
+                    post: Named::new(ap, ap.post(action))
+                })
+                .collect()
+        }
+    }
+}
+
+impl Serialize for ActionPlanner {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PlannerSer::new(self).serialize(serializer)
+    }
+}
+
+fn default_cost() -> i32 {
+    1
+}
+
+#[derive(Deserialize)]
+struct ActionDe {
+    name: String,
+    #[serde(default = "default_cost")]
+    cost: i32,
+    #[serde(default)]
+    pre: AtomValues,
+    #[serde(default)]
+    post: AtomValues
+}
+
+/// Registers the listed atoms in order, then the actions, adding any atoms they mention.
+fn build_planner<E: de::Error>(atoms: &[String], actions: &[ActionDe]) -> Result<ActionPlanner, E> {
+    let too_many = || E::custom("too many atoms");
+    let mut builder = ActionPlanner::builder();
+    for atom in atoms {
+        builder.register_atom(atom).ok_or_else(too_many)?;
+    }
+    for action in actions {
+        if builder.lookup_action(&action.name).is_some() {
+            return Err(E::custom(format!("duplicate action `{}`", action.name)));
+        }
+        builder.set_cost(&action.name, action.cost);
+        for &(ref atom, value) in &action.pre.0 {
+            if !builder.set_pre(&action.name, atom, value) {
+                return Err(too_many());
+            }
+        }
+        for &(ref atom, value) in &action.post.0 {
+            if !builder.set_post(&action.name, atom, value) {
+                return Err(too_many());
+            }
+        }
+    }
+    Ok(builder.build())
+}
+
+#[derive(Deserialize)]
+struct PlannerDe {
+    #[serde(default)]
+    atoms: Vec<String>,
+    actions: Vec<ActionDe>
+}
+
+impl<'de> Deserialize<'de> for ActionPlanner {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = PlannerDe::deserialize(deserializer)?;
+        build_planner(&repr.atoms, &repr.actions)
+    }
+}
+
+#[derive(Serialize)]
+struct ProblemSer<'a> {
+    #[serde(flatten)]
+    planner: PlannerSer<'a>,
+    start: Named<'a, WorldState>,
+    goal: Named<'a, WorldState>
+}
+
+/// A problem is written as its planner's `atoms` and `actions` followed by the `start` and
+/// `goal` states.
+impl Serialize for Problem {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ProblemSer {
+            planner: PlannerSer::new(&self.planner),
+            start: Named::new(&self.planner, &self.start),
+            goal: Named::new(&self.planner, &self.goal)
+        }.serialize(serializer)
+    }
+}
+
+#[derive(Deserialize)]
+struct ProblemDe {
+    #[serde(default)]
+    atoms: Vec<String>,
+    actions: Vec<ActionDe>,
+    #[serde(default)]
+    start: AtomValues,
+    #[serde(default)]
+    goal: AtomValues
+}
+
+impl<'de> Deserialize<'de> for Problem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = ProblemDe::deserialize(deserializer)?;
+        let planner = build_planner(&repr.atoms, &repr.actions)?;
+        let start = repr.start.resolve(&planner)?;
+        let goal = repr.goal.resolve(&planner)?;
+        Ok(Problem { planner, start, goal })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Named, NamedSeed};
+    use ::goap::{ActionPlanner, WorldState, Problem};
+    use ::astar::{AStar, AStarPlan};
+    use serde::de::DeserializeSeed;
+    use serde_json;
+
+    const DOMAIN: &str = r#"{
+        "atoms": ["alive"],
+        "actions": [
+            {"name": "load", "pre": {"armedwithgun": true}, "post": {"weaponloaded": true}},
+            {"name": "shoot", "cost": 2, "pre": {"weaponloaded": true}, "post": {"enemyalive": false}}
+        ],
+        "start": {"armedwithgun": true, "weaponloaded": false, "enemyalive": true, "alive": true},
+        "goal": {"enemyalive": false, "alive": true}
+    }"#;
+
+    #[test]
+    fn problem_round_trip() {
+        let problem: Problem = serde_json::from_str(DOMAIN).unwrap();
+        let planner = &problem.planner;
+        assert_eq!(Some(0), planner.lookup_atom("alive").map(|a| a.index()));
+        assert_eq!(2, planner.cost(planner.lookup_action("shoot").unwrap()));
+
+        let json = serde_json::to_string(&problem).unwrap();
+        let again: Problem = serde_json::from_str(&json).unwrap();
+        assert_eq!(json, serde_json::to_string(&again).unwrap());
+        assert_eq!(problem.start, again.start);
+        assert_eq!(problem.goal, again.goal);
+    }
+
+    #[test]
+    fn plan_round_trip() {
+        let problem: Problem = serde_json::from_str(DOMAIN).unwrap();
+        let ap = &problem.planner;
+        let plan = AStar::new().plan(ap, &problem.start, &problem.goal).unwrap();
+
+        let json = serde_json::to_string(&Named::new(ap, &plan)).unwrap();
+        let mut de = serde_json::Deserializer::from_str(&json);
+        let loaded = NamedSeed::<AStarPlan>::new(ap).deserialize(&mut de).unwrap();
+        assert_eq!(plan.cost(), loaded.cost());
+        assert!(plan.iter().eq(loaded.iter()));
+    }
+
+    #[test]
+    fn rejects_unknown_atoms() {
+        let ap: ActionPlanner = serde_json::from_str(r#"{"actions": [{"name": "load"}]}"#).unwrap();
+        let mut de = serde_json::Deserializer::from_str(r#"{"weaponlaoded": true}"#);
+        let result = NamedSeed::<WorldState>::new(&ap).deserialize(&mut de);
+        assert!(result.unwrap_err().to_string().contains("unknown atom `weaponlaoded`"));
+    }
+}
@@ -0,0 +1,37 @@
+//! Combat domains and helpers shared by the unit tests. Each domain adds its actions to a
+//! builder, so a test can register the extra actions it needs around them.
+
+use ::goap::{ActionPlanner, ActionPlannerBuilder, WorldState};
+use ::astar::AStarPlan;
+
+/// `load` the gun, then `shoot` (cost 2) the enemy with it.
+pub fn gunner(builder: &mut ActionPlannerBuilder) {
+    builder.action("load").requires("armedwithgun").sets("weaponloaded").build().unwrap();
+    builder.action("shoot").cost(2).requires("weaponloaded").clears("enemyalive").build().unwrap();
+}
+
+/// `scout` for the enemy and `load` the gun, `aim` once both are done, then `shoot` (cost 2).
+pub fn ambush(builder: &mut ActionPlannerBuilder) {
+    builder.action("scout").requires("armedwithgun").sets("enemyvisible").build().unwrap();
+    builder.action("load").requires("armedwithgun").sets("weaponloaded").build().unwrap();
+    builder.action("aim").requires("enemyvisible").requires("weaponloaded").sets("enemylinedup").build().unwrap();
+    builder.action("shoot").cost(2).requires("enemylinedup").clears("enemyalive").build().unwrap();
+}
+
+/// Armed, with the enemy alive and nothing else done yet.
+pub fn ambush_start(ap: &ActionPlanner) -> WorldState {
+    WorldState::builder(ap)
+        .holds("armedwithgun").lacks("enemyvisible").lacks("weaponloaded").lacks("enemylinedup")
+        .holds("enemyalive")
+        .build().unwrap()
+}
+
+/// The enemy is dead.
+pub fn enemy_dead(ap: &ActionPlanner) -> WorldState {
+    WorldState::builder(ap).lacks("enemyalive").build().unwrap()
+}
+
+/// The names of the actions `plan` takes, leaving out its `root` entry.
+pub fn step_names(plan: &AStarPlan) -> Vec<&str> {
+    plan.iter().skip(1).map(|(name, _)| &**name).collect()
+}